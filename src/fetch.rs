@@ -5,18 +5,45 @@ use std::{
     sync::{Arc, Mutex},
 };
 
-use grovedbg_types::{NodeFetchRequest, NodeUpdate, RootFetchRequest};
+use grovedbg_types::{NodeFetchRequest, NodeUpdate, PathQuery, RootFetchRequest};
 use reqwest::Client;
 use tokio::sync::mpsc::Receiver;
 
 use self::proto_conversion::BadProtoElement;
-use crate::model::{Key, Node, Path, Tree};
+pub(crate) use self::proto_conversion::ElementCtx;
+use crate::model::{
+    paging::{clamp_page_size, PageCursor},
+    reference::TargetStatus,
+    Element, Key, Node, Path, Tree,
+};
 
 pub(crate) enum Message {
     FetchRoot,
-    FetchNode { path: Path, key: Key },
-    FetchBranch { path: Path, key: Key },
-    UnloadSubtree { path: Path },
+    FetchNode {
+        path: Path,
+        key: Key,
+    },
+    FetchBranch {
+        path: Path,
+        key: Key,
+    },
+    UnloadSubtree {
+        path: Path,
+    },
+    FetchWithPathQuery {
+        path_query: PathQuery,
+    },
+    /// Find out what a reference target is without loading it into the tree
+    ResolveReference {
+        path: Path,
+        key: Key,
+    },
+    /// Fetch a page of subtree keys to list in the collapsed view
+    FetchKeysPage {
+        path: Path,
+        cursor: PageCursor,
+        page_size: usize,
+    },
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -31,6 +58,52 @@ fn base_url() -> String {
     web_sys::window().unwrap().location().origin().unwrap()
 }
 
+async fn fetch_node(client: &Client, path: &Path, key: &Key) -> Option<NodeUpdate> {
+    client
+        .post(format!("{}/fetch_node", base_url()))
+        .json(&NodeFetchRequest {
+            path: path.0.clone(),
+            key: key.clone(),
+        })
+        .send()
+        .await
+        .unwrap()
+        .json::<Option<NodeUpdate>>()
+        .await
+        .unwrap()
+}
+
+async fn fetch_with_path_query(client: &Client, path_query: &PathQuery) -> Vec<NodeUpdate> {
+    client
+        .post(format!("{}/fetch_with_path_query", base_url()))
+        .json(path_query)
+        .send()
+        .await
+        .unwrap()
+        .json::<Vec<NodeUpdate>>()
+        .await
+        .unwrap()
+}
+
+async fn resolve_target(client: &Client, path: &Path, key: &Key) -> TargetStatus {
+    if let Some(node_update) = fetch_node(client, path, key).await {
+        let node: Node = node_update.try_into().unwrap();
+        return TargetStatus::of_element(&node.element);
+    }
+
+    // The key is missing, but it's the subtree that could be missing as well
+    let Some((subtree_key, parent_path)) = path.split_last() else {
+        return TargetStatus::MissingKey;
+    };
+    let parent: Option<Node> = fetch_node(client, &parent_path.to_vec().into(), subtree_key)
+        .await
+        .map(|node_update| node_update.try_into().unwrap());
+    match parent.map(|node| node.element) {
+        Some(Element::Subtree { .. } | Element::Sumtree { .. }) => TargetStatus::MissingKey,
+        _ => TargetStatus::MissingPath,
+    }
+}
+
 pub(crate) async fn process_messages(mut receiver: Receiver<Message>, tree: Arc<Mutex<Tree>>) {
     let client = Client::new();
 
@@ -122,6 +195,51 @@ pub(crate) async fn process_messages(mut receiver: Receiver<Message>, tree: Arc<
                 let mut lock = tree.lock().unwrap();
                 lock.clear_subtree(&path);
             }
+            Message::FetchWithPathQuery { path_query } => {
+                let node_updates = fetch_with_path_query(&client, &path_query).await;
+
+                let mut lock = tree.lock().unwrap();
+                let mut results = Vec::new();
+                for node_update in node_updates {
+                    let path: Path = node_update.path.clone().into();
+                    let key = node_update.key.clone();
+                    lock.insert(path.clone(), key.clone(), node_update.try_into().unwrap());
+                    results.push((path, key));
+                }
+                lock.set_query_results(results);
+            }
+            Message::ResolveReference { path, key } => {
+                let status = resolve_target(&client, &path, &key).await;
+                let mut lock = tree.lock().unwrap();
+                lock.set_target_status(path, key, status);
+            }
+            Message::FetchKeysPage {
+                path,
+                mut cursor,
+                page_size,
+            } => {
+                let page_size = clamp_page_size(page_size);
+                let mut node_updates =
+                    fetch_with_path_query(&client, &cursor.path_query(&path, page_size)).await;
+                if node_updates.is_empty() && matches!(cursor, PageCursor::Before(_)) {
+                    // The page was the first one after a jump
+                    cursor = PageCursor::First;
+                    node_updates =
+                        fetch_with_path_query(&client, &cursor.path_query(&path, page_size)).await;
+                }
+
+                let mut lock = tree.lock().unwrap();
+                let mut keys = Vec::new();
+                for node_update in node_updates {
+                    keys.push(node_update.key.clone());
+                    lock.insert(
+                        path.clone(),
+                        node_update.key.clone(),
+                        node_update.try_into().unwrap(),
+                    );
+                }
+                lock.set_keys_page(&path, cursor.page(keys, page_size));
+            }
         }
     }
 }
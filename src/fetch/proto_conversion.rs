@@ -1,7 +1,7 @@
 //! Conversion definitions from received proto object to model.
 use grovedbg_types::{Key, Path, PathSegment};
 
-use crate::model::{Element, Node};
+use crate::model::{Element, FeatureType, Node};
 
 #[derive(Debug, thiserror::Error)]
 #[error("Computed reference has no key")]
@@ -18,36 +18,64 @@ impl<'a> TryFrom<ElementCtx<'a>> for Element {
 
     fn try_from(ElementCtx { element, path, key }: ElementCtx) -> Result<Self, Self::Error> {
         Ok(match element {
-            grovedbg_types::Element::Subtree { root_key } => Element::Subtree { root_key },
-            grovedbg_types::Element::Sumtree { root_key, sum } => {
+            grovedbg_types::Element::Subtree { root_key, .. } => Element::Subtree { root_key },
+            grovedbg_types::Element::Sumtree { root_key, sum, .. } => {
                 Element::Sumtree { root_key, sum }
             }
-            grovedbg_types::Element::Item { value } => Element::Item { value },
-            grovedbg_types::Element::SumItem { value } => Element::SumItem { value },
-            grovedbg_types::Element::AbsolutePathReference { path } => {
+            grovedbg_types::Element::Item { value, .. } => Element::Item { value },
+            grovedbg_types::Element::SumItem { value, .. } => Element::SumItem { value },
+            grovedbg_types::Element::AbsolutePathReference { path, .. } => {
                 from_absolute_path_reference(path)?
             }
             grovedbg_types::Element::UpstreamRootHeightReference {
                 n_keep,
                 path_append,
+                ..
             } => from_upstream_root_height_reference(path, n_keep, path_append)?,
             grovedbg_types::Element::UpstreamFromElementHeightReference {
                 n_remove,
                 path_append,
+                ..
             } => from_upstream_element_height_reference(path, n_remove, path_append)?,
-            grovedbg_types::Element::CousinReference { swap_parent } => {
+            grovedbg_types::Element::CousinReference { swap_parent, .. } => {
                 from_cousin_reference(path.to_vec(), key.to_vec(), swap_parent)?
             }
-            grovedbg_types::Element::RemovedCousinReference { swap_parent } => {
+            grovedbg_types::Element::RemovedCousinReference { swap_parent, .. } => {
                 from_removed_cousin_reference(path.to_vec(), key.to_vec(), swap_parent)?
             }
-            grovedbg_types::Element::SiblingReference { sibling_key } => {
+            grovedbg_types::Element::SiblingReference { sibling_key, .. } => {
                 from_sibling_reference(path.to_vec(), sibling_key)
             }
         })
     }
 }
 
+/// Element flags are kept by [Node] rather than by [Element], so they're
+/// taken before the element conversion.
+fn element_flags(element: &grovedbg_types::Element) -> Option<Vec<u8>> {
+    match element {
+        grovedbg_types::Element::Subtree { element_flags, .. }
+        | grovedbg_types::Element::Sumtree { element_flags, .. }
+        | grovedbg_types::Element::Item { element_flags, .. }
+        | grovedbg_types::Element::SumItem { element_flags, .. }
+        | grovedbg_types::Element::AbsolutePathReference { element_flags, .. }
+        | grovedbg_types::Element::UpstreamRootHeightReference { element_flags, .. }
+        | grovedbg_types::Element::UpstreamFromElementHeightReference { element_flags, .. }
+        | grovedbg_types::Element::CousinReference { element_flags, .. }
+        | grovedbg_types::Element::RemovedCousinReference { element_flags, .. }
+        | grovedbg_types::Element::SiblingReference { element_flags, .. } => element_flags.clone(),
+    }
+}
+
+impl From<grovedbg_types::TreeFeatureType> for FeatureType {
+    fn from(value: grovedbg_types::TreeFeatureType) -> Self {
+        match value {
+            grovedbg_types::TreeFeatureType::BasicMerkNode => FeatureType::Basic,
+            grovedbg_types::TreeFeatureType::SummedMerkNode(sum) => FeatureType::Summed(sum),
+        }
+    }
+}
+
 fn from_absolute_path_reference(
     mut path: grovedbg_types::Path,
 ) -> Result<Element, ReferenceWithoutKey> {
@@ -152,6 +180,7 @@ impl TryFrom<grovedbg_types::NodeUpdate> for Node {
 
     fn try_from(value: grovedbg_types::NodeUpdate) -> Result<Self, Self::Error> {
         Ok(Node {
+            element_flags: element_flags(&value.element),
             element: ElementCtx {
                 element: value.element,
                 path: &value.path,
@@ -160,6 +189,11 @@ impl TryFrom<grovedbg_types::NodeUpdate> for Node {
             .try_into()?,
             left_child: value.left_child,
             right_child: value.right_child,
+            value_hash: Some(value.value_hash),
+            kv_digest_hash: Some(value.kv_digest_hash),
+            left_merk_hash: value.left_merk_hash,
+            right_merk_hash: value.right_merk_hash,
+            feature_type: Some(value.feature_type.into()),
             ..Default::default()
         })
     }
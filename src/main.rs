@@ -6,13 +6,19 @@ mod ui;
 
 use std::sync::{Arc, Mutex};
 
-use eframe::egui::{self, emath::TSTransform, Visuals};
+use eframe::egui::{self, emath::TSTransform};
 use fetch::Message;
+use strum::IntoEnumIterator;
 use tokio::sync::mpsc::{channel, Receiver, Sender};
 
 use crate::{
-    model::Tree,
-    ui::{draw_legend, TreeDrawer},
+    model::{contract::Contracts, paging::MAX_PAGE_SIZE, Tree},
+    ui::{
+        draw_inspector, draw_legend, draw_minimap, handle_keyboard, CameraAnimation, CameraFocus,
+        ContractsWindow, ExportWindow, IntegrityWindow, PathQueryWindow, ProofViewer,
+        ReferenceEdgesMode, ReferencesWindow, Theme, ThemeWindow, TreeDrawer, TreeViewOptions,
+        KEY_BINDINGS_HINT, THEME_STORAGE_KEY,
+    },
 };
 
 #[cfg(not(target_arch = "wasm32"))]
@@ -46,10 +52,30 @@ fn main() {
     });
 }
 
+/// Which tree is drawn on the canvas
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum View {
+    #[default]
+    Database,
+    Proof,
+}
+
 struct App {
     transform: TSTransform,
     tree: Arc<Mutex<Tree>>,
     sender: Sender<Message>,
+    contracts: Contracts,
+    contracts_window: ContractsWindow,
+    proof_viewer: ProofViewer,
+    path_query_window: PathQueryWindow,
+    integrity_window: IntegrityWindow,
+    references_window: ReferencesWindow,
+    theme_window: ThemeWindow,
+    export_window: ExportWindow,
+    view: View,
+    view_options: TreeViewOptions,
+    /// Transition of the current view to a focused part of the tree
+    camera_animation: Option<CameraAnimation>,
 }
 
 impl App {
@@ -62,33 +88,136 @@ impl App {
             transform: Default::default(),
             tree,
             sender,
+            contracts: Default::default(),
+            contracts_window: Default::default(),
+            proof_viewer: Default::default(),
+            path_query_window: Default::default(),
+            integrity_window: Default::default(),
+            references_window: Default::default(),
+            theme_window: Default::default(),
+            export_window: Default::default(),
+            view: Default::default(),
+            view_options: TreeViewOptions {
+                theme: cc
+                    .storage
+                    .and_then(|storage| storage.get_string(THEME_STORAGE_KEY))
+                    .and_then(|json| Theme::from_json(&json))
+                    .unwrap_or_default(),
+                ..Default::default()
+            },
+            camera_animation: None,
         }
     }
 }
 
 impl eframe::App for App {
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        storage.set_string(THEME_STORAGE_KEY, self.view_options.theme.to_json());
+    }
+
     fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        if self.proof_viewer.show(ctx) {
+            self.view = View::Proof;
+        }
+
+        match self.view {
+            View::Database => {
+                let lock = self.tree.lock().unwrap();
+                draw_inspector(ctx, &lock, &self.contracts, &self.view_options.theme);
+            }
+            View::Proof => draw_inspector(
+                ctx,
+                &self.proof_viewer.tree,
+                &self.contracts,
+                &self.view_options.theme,
+            ),
+        }
+
         egui::CentralPanel::default().show(ctx, |ui| {
-            ctx.set_visuals(Visuals::dark());
+            ctx.set_visuals(self.view_options.theme.visuals());
 
-            ui.label("GroveDB Visualizer");
+            let mut fit_all = false;
+            ui.horizontal(|header| {
+                header.label("GroveDB Visualizer");
+                header.label("⌨").on_hover_text(KEY_BINDINGS_HINT);
+                if header.button("Data contracts").clicked() {
+                    self.contracts_window.toggle();
+                }
+                if header.button("Path query").clicked() {
+                    self.path_query_window.toggle();
+                }
+                if header.button("Integrity").clicked() {
+                    self.integrity_window.toggle();
+                }
+                if header.button("References").clicked() {
+                    self.references_window.toggle();
+                }
+                if header.button("Proof").clicked() {
+                    self.proof_viewer.toggle();
+                }
+                if header.button("Theme").clicked() {
+                    self.theme_window.toggle();
+                }
+                if header.button("Export").clicked() {
+                    self.export_window.toggle();
+                }
+                header.separator();
+                if header
+                    .selectable_value(&mut self.view, View::Database, "Database")
+                    .union(header.selectable_value(&mut self.view, View::Proof, "Proof tree"))
+                    .changed()
+                {
+                    self.camera_animation = None;
+                }
+                header.separator();
+                if header.button("Fit all").clicked() {
+                    fit_all = true;
+                }
+                header.checkbox(&mut self.view_options.avl_overlay, "AVL overlay");
+                header.checkbox(&mut self.view_options.minimap, "Minimap");
+                header.add(
+                    egui::DragValue::new(&mut self.view_options.page_size)
+                        .clamp_range(1..=MAX_PAGE_SIZE)
+                        .prefix("Page size: "),
+                );
+                egui::ComboBox::from_label("Reference edges")
+                    .selected_text(self.view_options.reference_edges.as_ref())
+                    .show_ui(header, |menu| {
+                        ReferenceEdgesMode::iter().for_each(|mode| {
+                            menu.selectable_value(
+                                &mut self.view_options.reference_edges,
+                                mode,
+                                mode.as_ref(),
+                            );
+                        });
+                    });
+            });
             ui.separator();
 
             let (id, rect) = ui.allocate_space(ui.available_size());
 
             let response = ui.interact(rect, id, egui::Sense::click_and_drag());
+            let transform = match self.view {
+                View::Database => &mut self.transform,
+                View::Proof => &mut self.proof_viewer.transform,
+            };
+            // Any manual navigation takes over the animation
+            if response.dragged() || response.double_clicked() {
+                self.camera_animation = None;
+            }
+
             // Allow dragging the background as well.
             if response.dragged() {
-                self.transform.translation += response.drag_delta();
+                transform.translation += response.drag_delta();
             }
 
             // Plot-like reset
             if response.double_clicked() {
-                self.transform = TSTransform::default();
+                *transform = TSTransform::default();
             }
 
             let local_transform =
-                TSTransform::from_translation(ui.min_rect().left_top().to_vec2()) * self.transform;
+                TSTransform::from_translation(ui.min_rect().left_top().to_vec2()) * *transform;
 
             if let Some(pointer) = ui.ctx().input(|i| i.pointer.hover_pos()) {
                 // Note: doesn't catch zooming / panning if a button in this PanZoom container
@@ -99,23 +228,134 @@ impl eframe::App for App {
                     let pan_delta = ui.ctx().input(|i| i.smooth_scroll_delta);
 
                     // Zoom in on pointer:
-                    self.transform = self.transform
+                    *transform = *transform
                         * TSTransform::from_translation(pointer_in_layer.to_vec2())
                         * TSTransform::from_scaling(zoom_delta)
                         * TSTransform::from_translation(-pointer_in_layer.to_vec2());
 
                     // Pan:
-                    self.transform = TSTransform::from_translation(pan_delta) * self.transform;
+                    *transform = TSTransform::from_translation(pan_delta) * *transform;
+
+                    if zoom_delta != 1.0 || pan_delta != egui::Vec2::ZERO {
+                        self.camera_animation = None;
+                    }
                 }
             }
 
-            {
-                let lock = self.tree.lock().unwrap();
-                let drawer = TreeDrawer::new(ui, self.transform, rect, &lock, &self.sender);
-                drawer.draw_tree();
+            if let Some(animation) = &self.camera_animation {
+                let (animated, finished) = animation.transform_at(ctx.input(|i| i.time), rect);
+                *transform = animated;
+                if finished {
+                    self.camera_animation = None;
+                } else {
+                    ctx.request_repaint();
+                }
             }
 
-            draw_legend(ui);
+            let transform = *transform;
+            let focus = match self.view {
+                View::Database => {
+                    let lock = self.tree.lock().unwrap();
+                    let jumped = draw_legend(
+                        ui,
+                        &lock,
+                        &self.view_options.theme,
+                        &mut self.view_options.element_types,
+                    );
+                    let selection_moved = handle_keyboard(ctx, &lock, &self.sender) || jumped;
+                    let drawer = TreeDrawer::new(
+                        ui,
+                        transform,
+                        rect,
+                        &lock,
+                        Some(&self.sender),
+                        &self.contracts,
+                        &self.view_options,
+                    );
+                    let focus = drawer.draw_tree();
+                    if self.view_options.minimap {
+                        draw_minimap(
+                            ui,
+                            &lock,
+                            &self.view_options.theme,
+                            &mut self.transform,
+                            rect,
+                        );
+                    }
+                    // Follow the selection when it goes off-screen
+                    let selection_focus = selection_moved
+                        .then(|| lock.selected_node())
+                        .flatten()
+                        .and_then(|(path, key)| lock.subtrees.get(&path)?.get_node_input(&key))
+                        .filter(|point| !rect.contains(transform * *point))
+                        .map(CameraFocus::Center);
+                    focus.or(selection_focus).or_else(|| {
+                        fit_all
+                            .then(|| lock.visible_bounds())
+                            .flatten()
+                            .map(CameraFocus::Fit)
+                    })
+                }
+                View::Proof => {
+                    draw_legend(
+                        ui,
+                        &self.proof_viewer.tree,
+                        &self.view_options.theme,
+                        &mut self.view_options.element_types,
+                    );
+                    let drawer = TreeDrawer::new(
+                        ui,
+                        transform,
+                        rect,
+                        &self.proof_viewer.tree,
+                        None,
+                        &self.contracts,
+                        &self.view_options,
+                    );
+                    let focus = drawer.draw_tree();
+                    if self.view_options.minimap {
+                        draw_minimap(
+                            ui,
+                            &self.proof_viewer.tree,
+                            &self.view_options.theme,
+                            &mut self.proof_viewer.transform,
+                            rect,
+                        );
+                    }
+                    focus.or_else(|| {
+                        fit_all
+                            .then(|| self.proof_viewer.tree.visible_bounds())
+                            .flatten()
+                            .map(CameraFocus::Fit)
+                    })
+                }
+            };
+            if let Some(focus) = focus {
+                self.camera_animation = Some(CameraAnimation::new(
+                    transform,
+                    focus.transform(transform, rect),
+                    ctx.input(|i| i.time),
+                ));
+                ctx.request_repaint();
+            }
+
+            self.contracts_window.show(ctx, &mut self.contracts);
+            self.theme_window.show(ctx, &mut self.view_options.theme);
+            {
+                let mut lock = self.tree.lock().unwrap();
+                self.path_query_window
+                    .show(ctx, &mut lock, &self.sender, &self.view_options.theme);
+                self.integrity_window.show(ctx, &lock);
+                self.references_window.show(ctx, &mut lock);
+                self.references_window
+                    .dispatch(ctx, &mut lock, &self.sender);
+                let export_tree = match self.view {
+                    View::Database => &lock,
+                    View::Proof => &self.proof_viewer.tree,
+                };
+                self.export_window
+                    .show(ctx, export_tree, &self.view_options, transform, rect);
+            }
         });
     }
 }
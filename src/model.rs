@@ -1,4 +1,15 @@
 pub(crate) mod alignment;
+pub(crate) mod avl;
+pub(crate) mod contract;
+pub(crate) mod export;
+pub(crate) mod hash;
+pub(crate) mod integrity;
+pub(crate) mod listing;
+pub(crate) mod navigation;
+pub(crate) mod paging;
+pub(crate) mod proof;
+pub(crate) mod reference;
+pub(crate) mod sum;
 
 use std::{
     cell::{RefCell, RefMut},
@@ -9,10 +20,18 @@ use std::{
 
 use eframe::{egui, epaint::Pos2};
 
-use self::alignment::{
-    expanded_subtree_dimentions, COLLAPSED_SUBTREE_HEIGHT, COLLAPSED_SUBTREE_WIDTH,
+use self::{
+    alignment::{
+        expanded_subtree_layout, ExpandedLayout, NodePlacement, COLLAPSED_SUBTREE_HEIGHT,
+        COLLAPSED_SUBTREE_WIDTH,
+    },
+    avl::Heights,
+    hash::CryptoHash,
+    listing::ListingFilter,
+    paging::KeysPage,
+    reference::TargetStatus,
 };
-use crate::ui::DisplayVariant;
+use crate::ui::{DisplayVariant, HexDumpState};
 
 #[derive(Debug, PartialEq, Eq, Clone, Default, Hash)]
 pub(crate) struct Path(pub Vec<Vec<u8>>);
@@ -94,11 +113,23 @@ impl<'a> SetVisibility<'a> {
     }
 }
 
+/// Loaded references grouped by their targets
+type ReferrersIndex = BTreeMap<(Path, Key), Vec<(Path, Key)>>;
+
 /// Structure that holds the currently known state of GroveDB.
 #[derive(Debug, Default)]
 pub(crate) struct Tree {
     pub(crate) subtrees: BTreeMap<Path, Subtree>,
-    pub(crate) levels_dimentions: RefCell<Vec<(f32, f32)>>,
+    /// Node picked by user to be shown in the inspector
+    selected_node: RefCell<Option<(Path, Key)>>,
+    /// Nodes returned by the last path query in order of their arrival
+    query_results: Vec<(Path, Key)>,
+    /// Fetched statuses of references targets that are not loaded
+    reference_targets: BTreeMap<(Path, Key), TargetStatus>,
+    /// References targets sent to be resolved by the fetch layer
+    pending_targets: BTreeSet<(Path, Key)>,
+    /// Lazily built index of loaded references by their targets
+    referrers: RefCell<Option<ReferrersIndex>>,
 }
 
 impl Tree {
@@ -106,31 +137,49 @@ impl Tree {
         Default::default()
     }
 
-    pub(crate) fn update_dimensions(&self) {
-        for (path, subtree) in self.subtrees.iter() {
-            if !subtree.visible() {
-                continue;
-            }
+    pub(crate) fn select_node(&self, path: Path, key: Key) {
+        *self.selected_node.borrow_mut() = Some((path, key));
+    }
 
-            subtree.ui_state.borrow_mut().children_width = 0.;
-            let mut levels_height = self.levels_dimentions.borrow_mut();
-            if levels_height.len() < path.len() + 1 {
-                levels_height.push(Default::default());
-            }
-            levels_height[path.len()].0 = 0.0;
-
-            let (width, height) = subtree.update_base_dimensions();
-            // Propagate width to parent subtrees
-            for depth in (0..path.len()).rev() {
-                let parent_path = &path[0..depth].to_vec().into();
-                if let Some(parent) = self.subtrees.get(&parent_path) {
-                    parent.ui_state.borrow_mut().children_width += width;
-                    levels_height[parent_path.len()].0 += width;
-                }
-            }
+    pub(crate) fn clear_selection(&self) {
+        *self.selected_node.borrow_mut() = None;
+    }
 
-            levels_height[path.len()].1 = levels_height[path.len()].1.max(height);
-        }
+    pub(crate) fn selected_node(&self) -> Option<(Path, Key)> {
+        self.selected_node.borrow().clone()
+    }
+
+    pub(crate) fn is_selected(&self, path: &Path, key: KeySlice) -> bool {
+        self.selected_node
+            .borrow()
+            .as_ref()
+            .map(|(p, k)| p == path && k == key)
+            .unwrap_or_default()
+    }
+
+    /// Remember path query results and make subtrees containing them visible.
+    pub(crate) fn set_query_results(&mut self, results: Vec<(Path, Key)>) {
+        results
+            .iter()
+            .for_each(|(path, _)| self.reveal_subtree(path));
+        self.query_results = results;
+    }
+
+    /// Make the subtree and all its parent subtrees visible.
+    pub(crate) fn reveal_subtree(&self, path: &Path) {
+        (0..=path.len())
+            .filter_map(|depth| self.subtrees.get(&path[0..depth].to_vec().into()))
+            .for_each(|subtree| subtree.set_visible(true));
+    }
+
+    pub(crate) fn query_results(&self) -> &[(Path, Key)] {
+        &self.query_results
+    }
+
+    pub(crate) fn is_query_result(&self, path: &Path, key: KeySlice) -> bool {
+        self.query_results
+            .iter()
+            .any(|(p, k)| p == path && k == key)
     }
 
     pub(crate) fn set_root(&mut self, root_key: Key) {
@@ -183,13 +232,17 @@ impl Tree {
         self.subtrees
             .get_mut(&path)
             .expect("model was updated")
-            .insert(key, node);
+            .insert(key.clone(), node);
+        self.forget_target_status(&path, &key);
+        self.invalidate_referrers();
     }
 
     pub(crate) fn remove(&mut self, path: &Path, key: KeySlice) {
         if let Some(subtree) = self.subtrees.get_mut(path) {
             subtree.remove(key);
         }
+        self.forget_target_status(path, key);
+        self.invalidate_referrers();
     }
 
     /// The data structure guarantees  that for a node representing a subtree
@@ -199,7 +252,11 @@ impl Tree {
     pub(crate) fn clear_subtree(&mut self, path: &Path) {
         if let Some(subtree) = self.subtrees.get_mut(path) {
             subtree.nodes.clear();
+            subtree.keys_page = None;
+            subtree.invalidate_heights();
+            subtree.invalidate_layout();
         }
+        self.invalidate_referrers();
     }
 
     /// For a given path ensures all subtrees exist and each of them contains a
@@ -224,13 +281,25 @@ pub(crate) struct SubtreeUiState {
     pub(crate) expanded: bool,
     pub(crate) input_point: Pos2,
     pub(crate) output_point: Pos2,
-    pub(crate) page: usize,
     pub(crate) visible: bool,
     pub(crate) width: f32,
-    pub(crate) children_width: f32,
     pub(crate) height: f32,
-    pub(crate) levels: u32,
-    pub(crate) leafs: u32,
+    /// Top center of the subtree on the canvas
+    pub(crate) position: Pos2,
+    /// Nodes placement if the subtree is expanded
+    pub(crate) layout: ExpandedLayout,
+    /// Layout is up to date with nodes, visibility and expansion
+    pub(crate) layout_valid: bool,
+    /// Key to start the collapsed view page from
+    pub(crate) jump_key: String,
+    /// Representation the jump key is typed in
+    pub(crate) jump_key_display_variant: DisplayVariant,
+    /// Which loaded keys to list in the collapsed view
+    pub(crate) listing_filter: ListingFilter,
+    /// Export descendant subtrees with the subtree's data
+    pub(crate) export_descendants: bool,
+    /// Outcome of the last data export, a message or an error
+    pub(crate) export_status: Option<Result<String, String>>,
 }
 
 /// Subtree holds all the info about one specific subtree of GroveDB
@@ -251,6 +320,10 @@ pub(crate) struct Subtree {
     /// Subtree nodes' keys to keep track of nodes that are not yet fetched but
     /// referred by parent node
     waitlist: HashSet<Key>,
+    /// Lazily computed AVL heights of nodes with fully loaded branches
+    heights: RefCell<Option<BTreeMap<Key, Heights>>>,
+    /// Keys listed in the collapsed view as fetched from GroveDB
+    keys_page: Option<KeysPage>,
     /// UI state of a subtree
     ui_state: RefCell<SubtreeUiState>,
 }
@@ -260,32 +333,32 @@ impl Subtree {
         self.nodes.is_empty()
     }
 
-    fn update_base_dimensions(&self) -> (f32, f32) {
+    fn update_base_dimensions(&self) {
         let mut state = self.ui_state.borrow_mut();
         if state.expanded {
-            let (width, height, levels, leafs) = expanded_subtree_dimentions(self);
-            state.width = width;
-            state.height = height;
-            state.levels = levels;
-            state.leafs = leafs;
+            state.layout = expanded_subtree_layout(self);
+            state.width = state.layout.width;
+            state.height = state.layout.height;
         } else {
             state.width = COLLAPSED_SUBTREE_WIDTH;
             state.height = COLLAPSED_SUBTREE_HEIGHT;
         }
-        (state.width, state.height)
     }
 
-    pub(crate) fn levels(&self) -> u32 {
-        self.ui_state.borrow().levels
+    pub(crate) fn width(&self) -> f32 {
+        self.ui_state.borrow().width
+    }
+
+    pub(crate) fn height(&self) -> f32 {
+        self.ui_state.borrow().height
     }
 
-    pub(crate) fn leafs(&self) -> u32 {
-        self.ui_state.borrow().leafs
+    pub(crate) fn position(&self) -> Pos2 {
+        self.ui_state.borrow().position
     }
 
-    pub(crate) fn width(&self) -> f32 {
-        let state = self.ui_state.borrow();
-        state.width.max(state.children_width)
+    pub(crate) fn expanded_layout(&self) -> Vec<NodePlacement> {
+        self.ui_state.borrow().layout.nodes.clone()
     }
 
     fn new() -> Self {
@@ -304,41 +377,28 @@ impl Subtree {
     }
 
     pub(crate) fn set_visible(&self, visible: bool) {
-        self.ui_state.borrow_mut().visible = visible;
-    }
-
-    pub(crate) fn page_idx(&self) -> usize {
-        self.ui_state.borrow().page
-    }
-
-    pub(crate) fn next_page(&self) {
-        self.ui_state.borrow_mut().page += 1;
-    }
-
-    pub(crate) fn prev_page(&self) {
-        let page: &mut usize = &mut self.ui_state.borrow_mut().page;
-
-        if *page > 0 {
-            *page -= 1;
+        if self.visible() != visible {
+            self.ui_state.borrow_mut().visible = visible;
+            self.invalidate_layout();
         }
     }
 
-    pub(crate) fn n_nodes(&self) -> usize {
-        self.nodes.len()
-    }
-
     pub(crate) fn is_expanded(&self) -> bool {
         self.ui_state.borrow().expanded
     }
 
     pub(crate) fn set_expanded(&self) {
-        if !self.is_empty() {
+        if !self.is_empty() && !self.is_expanded() {
             self.ui_state.borrow_mut().expanded = true;
+            self.invalidate_layout();
         }
     }
 
     pub(crate) fn set_collapsed(&self) {
-        self.ui_state.borrow_mut().expanded = false;
+        if self.is_expanded() {
+            self.ui_state.borrow_mut().expanded = false;
+            self.invalidate_layout();
+        }
     }
 
     pub(crate) fn set_input_point(&self, input_point: Pos2) {
@@ -418,6 +478,7 @@ impl Subtree {
     fn set_root(&mut self, root_node: Key) -> &mut Self {
         self.cluster_roots.remove(&root_node);
         self.root_node = Some(root_node);
+        self.invalidate_layout();
         self
     }
 
@@ -432,6 +493,9 @@ impl Subtree {
     /// taken care of.
     fn remove(&mut self, key: KeySlice) {
         if let Some(node) = self.nodes.remove(key) {
+            self.invalidate_heights();
+            self.invalidate_layout();
+
             // Update the waitlist since no one is waiting for these children anymore :(
             node.left_child.iter().for_each(|child| {
                 self.waitlist.remove(child);
@@ -515,6 +579,8 @@ impl Subtree {
 
         // Finally insert the node
         self.nodes.insert(key, node);
+        self.invalidate_heights();
+        self.invalidate_layout();
     }
 
     fn insert_not_exists(&mut self, key: Key, node: Node) {
@@ -580,6 +646,10 @@ impl<'a> SubtreeCtx<'a> {
         self.path
     }
 
+    pub(crate) fn tree(&self) -> &'a Tree {
+        self.set_child_visibility.tree
+    }
+
     // pub(crate) fn iter_cluster_roots(&self) -> impl ExactSizeIterator<Item =
     // NodeCtx> {     self.subtree.cluster_roots.iter().map(|key| NodeCtx {
     //         node: self
@@ -647,10 +717,27 @@ impl<'a> NodeCtx<'a> {
 
     pub(crate) fn set_left_visible(&self) {
         self.node.ui_state.borrow_mut().show_left = true;
+        self.subtree().invalidate_layout();
     }
 
     pub(crate) fn set_right_visible(&self) {
         self.node.ui_state.borrow_mut().show_right = true;
+        self.subtree().invalidate_layout();
+    }
+
+    /// Pick the node to be shown in the inspector
+    pub(crate) fn select(&self) {
+        self.subtree_ctx
+            .tree()
+            .select_node(self.path.clone(), self.key.to_vec());
+    }
+
+    pub(crate) fn is_query_result(&self) -> bool {
+        self.subtree_ctx.tree().is_query_result(self.path, self.key)
+    }
+
+    pub(crate) fn is_selected(&self) -> bool {
+        self.subtree_ctx.tree().is_selected(self.path, self.key)
     }
 }
 
@@ -659,6 +746,8 @@ impl<'a> NodeCtx<'a> {
 pub(crate) struct NodeUiState {
     pub(crate) key_display_variant: DisplayVariant,
     pub(crate) item_display_variant: DisplayVariant,
+    pub(crate) flags_display_variant: DisplayVariant,
+    pub(crate) hex_dump: HexDumpState,
     pub(crate) input_point: Pos2,
     pub(crate) output_point: Pos2,
     pub(crate) left_sibling_point: Pos2,
@@ -671,8 +760,23 @@ pub(crate) struct NodeUiState {
 #[cfg_attr(test, derive(PartialEq))]
 pub(crate) struct Node {
     pub(crate) element: Element,
+    /// Element flags, used by Platform for storage epochs and owner ids
+    pub(crate) element_flags: Option<Vec<u8>>,
+    /// Merk tree feature type of the node, `None` if not received
+    pub(crate) feature_type: Option<FeatureType>,
     pub(crate) left_child: Option<Key>,
     pub(crate) right_child: Option<Key>,
+    /// Hash of the element's serialized value as received from GroveDB
+    pub(crate) value_hash: Option<CryptoHash>,
+    /// Hash of the key and the value hash as received from GroveDB
+    pub(crate) kv_digest_hash: Option<CryptoHash>,
+    /// Node hash of the left child as received from GroveDB
+    pub(crate) left_merk_hash: Option<CryptoHash>,
+    /// Node hash of the right child as received from GroveDB
+    pub(crate) right_merk_hash: Option<CryptoHash>,
+    /// Node hash of a node known only by hashes, such as pruned branches of a
+    /// proof
+    pub(crate) ghost: Option<CryptoHash>,
     pub(crate) ui_state: RefCell<NodeUiState>,
 }
 
@@ -726,6 +830,14 @@ impl Node {
         }
     }
 
+    /// Value this node adds to a sum of its sumtree
+    pub(crate) fn sum_contribution(&self) -> Option<i64> {
+        match self.feature_type {
+            Some(FeatureType::Summed(sum)) => Some(sum),
+            _ => None,
+        }
+    }
+
     pub(crate) fn with_left_child(mut self, key: Key) -> Self {
         self.left_child = Some(key);
         self
@@ -737,6 +849,13 @@ impl Node {
     }
 }
 
+/// Merk tree feature type, summed nodes are the ones of sumtrees
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum FeatureType {
+    Basic,
+    Summed(i64),
+}
+
 /// A value that a subtree's node hold
 #[derive(Debug, Clone, Default, PartialEq, strum::EnumIter, strum::AsRefStr)]
 pub(crate) enum Element {
@@ -1,65 +1,407 @@
 //! Nodes/Subtrees alignment implementation.
+//!
+//! Both expanded Merk trees and the hierarchy of subtrees get a tidy layout:
+//! each branch is laid out on its own, then siblings are put as close to each
+//! other as their contours allow and a parent is centered above them.
 
-use super::Subtree;
+use std::collections::{BTreeMap, BTreeSet};
 
-const NODE_WIDTH: f32 = 150.;
+use eframe::epaint::{Pos2, Rect, Vec2};
+
+use super::{Key, Path, Subtree, Tree};
+
+pub(crate) const NODE_WIDTH: f32 = 150.;
 pub(crate) const NODE_HEIGHT: f32 = 200.;
 pub(crate) const COLLAPSED_SUBTREE_WIDTH: f32 = 400.;
 pub(crate) const COLLAPSED_SUBTREE_HEIGHT: f32 = 600.;
+/// Horizontal space between neighbour subtrees
+const SUBTREES_GAP: f32 = 50.;
+/// Vertical space between levels of subtrees
+const LEVELS_GAP: f32 = 100.;
+/// Top center of the root subtree
+const ORIGIN: Pos2 = Pos2::new(500., 100.);
+
+/// Horizontal extents of a laid out branch for each of its levels, relative to
+/// the branch root.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Contour(Vec<(f32, f32)>);
+
+impl Contour {
+    fn new(width: f32) -> Self {
+        Contour(vec![(-width / 2., width / 2.)])
+    }
 
-fn leaves_level_count(n_levels: u32) -> u32 {
-    if n_levels > 0 {
-        2u32.pow(n_levels - 1)
-    } else {
-        0
+    /// Offset for the `right` branch root to be placed next to this branch
+    /// without overlapping.
+    fn separation(&self, right: &Contour, gap: f32) -> f32 {
+        self.0
+            .iter()
+            .zip(right.0.iter())
+            .map(|((_, self_right), (right_left, _))| self_right - right_left + gap)
+            .fold(f32::MIN, f32::max)
     }
+
+    fn merge(&mut self, other: &Contour, offset: f32) {
+        for (level, (left, right)) in other.0.iter().enumerate() {
+            let (left, right) = (left + offset, right + offset);
+            match self.0.get_mut(level) {
+                Some(extents) => *extents = (extents.0.min(left), extents.1.max(right)),
+                None => self.0.push((left, right)),
+            }
+        }
+    }
+
+    /// Put the contour of children one level below the parent's one.
+    fn with_children(mut self, children: Contour) -> Self {
+        self.0.extend(children.0);
+        self
+    }
+
+    fn extents(&self) -> (f32, f32) {
+        self.0
+            .iter()
+            .fold((f32::MAX, f32::MIN), |(min, max), (left, right)| {
+                (min.min(*left), max.max(*right))
+            })
+    }
+}
+
+/// Place sibling branches left to right as close as possible, returns their
+/// offsets centered around zero and the merged contour.
+fn place_siblings<'c>(
+    contours: impl IntoIterator<Item = &'c Contour>,
+    gap: f32,
+) -> (Vec<f32>, Contour) {
+    let mut merged = Contour::default();
+    let mut offsets = Vec::new();
+    for contour in contours {
+        let offset = if offsets.is_empty() {
+            0.
+        } else {
+            merged.separation(contour, gap)
+        };
+        merged.merge(contour, offset);
+        offsets.push(offset);
+    }
+    let middle = (offsets.first().copied().unwrap_or_default()
+        + offsets.last().copied().unwrap_or_default())
+        / 2.;
+    offsets.iter_mut().for_each(|offset| *offset -= middle);
+    let mut centered = Contour::default();
+    centered.merge(&merged, -middle);
+    (offsets, centered)
+}
+
+/// Position of an expanded subtree node
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct NodePlacement {
+    pub(crate) key: Key,
+    pub(crate) parent: Option<Key>,
+    /// Offset of the node's top center from the subtree's top center
+    pub(crate) offset: Vec2,
 }
 
-pub(super) fn expanded_subtree_dimentions(subtree: &Subtree) -> (f32, f32, u32, u32) {
-    if let Some(root_node) = subtree.root_node() {
-        let mut queue = vec![(1, root_node)];
-        let mut levels = 0;
-        while let Some((level, node)) = queue.pop() {
-            levels = levels.max(level);
-            let state = node.ui_state.borrow();
-            state
-                .show_left
-                .then_some(node.left_child.as_ref())
-                .flatten()
-                .and_then(|key| subtree.nodes.get(key))
-                .into_iter()
-                .for_each(|node| queue.push((level + 1, node)));
-            state
-                .show_right
-                .then_some(node.right_child.as_ref())
-                .flatten()
-                .and_then(|key| subtree.nodes.get(key))
-                .into_iter()
-                .for_each(|node| queue.push((level + 1, node)));
+/// Layout of an expanded subtree, parents go before their children.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct ExpandedLayout {
+    pub(crate) nodes: Vec<NodePlacement>,
+    pub(crate) width: f32,
+    pub(crate) height: f32,
+}
+
+struct Branch {
+    nodes: Vec<NodePlacement>,
+    contour: Contour,
+}
+
+/// Lay out a node with its visible descendants relative to the node, nodes
+/// reachable again through a cycle are skipped.
+fn layout_branch<'a>(
+    subtree: &'a Subtree,
+    key: &'a Key,
+    parent: Option<&Key>,
+    visited: &mut BTreeSet<&'a Key>,
+) -> Option<Branch> {
+    let node = subtree.nodes.get(key)?;
+    if !visited.insert(key) {
+        return None;
+    }
+    let (show_left, show_right) = {
+        let state = node.ui_state.borrow();
+        (state.show_left, state.show_right)
+    };
+    let left = node
+        .left_child
+        .as_ref()
+        .filter(|_| show_left)
+        .and_then(|child| layout_branch(subtree, child, Some(key), visited));
+    let right = node
+        .right_child
+        .as_ref()
+        .filter(|_| show_right)
+        .and_then(|child| layout_branch(subtree, child, Some(key), visited));
+
+    // A single child still goes to its side to tell left from right
+    let (left_offset, right_offset, children_contour) = match (&left, &right) {
+        (Some(left), Some(right)) => {
+            let (offsets, contour) = place_siblings([&left.contour, &right.contour], 0.);
+            (offsets[0], offsets[1], contour)
+        }
+        (Some(left), None) => {
+            let mut contour = Contour::default();
+            contour.merge(&left.contour, -NODE_WIDTH / 2.);
+            (-NODE_WIDTH / 2., 0., contour)
         }
-        let leafs = leaves_level_count(levels);
+        (None, Some(right)) => {
+            let mut contour = Contour::default();
+            contour.merge(&right.contour, NODE_WIDTH / 2.);
+            (0., NODE_WIDTH / 2., contour)
+        }
+        (None, None) => (0., 0., Contour::default()),
+    };
 
+    let mut nodes = vec![NodePlacement {
+        key: key.clone(),
+        parent: parent.cloned(),
+        offset: Vec2::ZERO,
+    }];
+    for (branch, x) in [(left, left_offset), (right, right_offset)] {
+        if let Some(branch) = branch {
+            nodes.extend(branch.nodes.into_iter().map(|mut placement| {
+                placement.offset += Vec2::new(x, NODE_HEIGHT);
+                placement
+            }));
+        }
+    }
+
+    Some(Branch {
+        nodes,
+        contour: Contour::new(NODE_WIDTH).with_children(children_contour),
+    })
+}
+
+pub(super) fn expanded_subtree_layout(subtree: &Subtree) -> ExpandedLayout {
+    let Some(branch) = subtree
+        .root_node
+        .as_ref()
+        .and_then(|root| layout_branch(subtree, root, None, &mut BTreeSet::new()))
+    else {
+        return Default::default();
+    };
+    let (min, max) = branch.contour.extents();
+    // Center the subtree's bounding box rather than the root node
+    let shift = Vec2::new(-(min + max) / 2., 0.);
+    ExpandedLayout {
+        nodes: branch
+            .nodes
+            .into_iter()
+            .map(|mut placement| {
+                placement.offset += shift;
+                placement
+            })
+            .collect(),
+        width: max - min,
+        height: branch.contour.0.len() as f32 * NODE_HEIGHT,
+    }
+}
+
+impl Subtree {
+    /// Have the subtree laid out again, shall be called on nodes updates and
+    /// visibility or expansion changes.
+    pub(crate) fn invalidate_layout(&self) {
+        self.ui_state.borrow_mut().layout_valid = false;
+    }
+
+    /// Area taken by the subtree according to the last layout
+    pub(crate) fn bounding_rect(&self) -> Rect {
+        let state = self.ui_state.borrow();
+        Rect::from_min_size(
+            state.position - Vec2::new(state.width / 2., 0.),
+            Vec2::new(state.width, state.height),
+        )
+    }
+}
+
+impl Tree {
+    /// Area taken by all visible subtrees according to the last layout
+    pub(crate) fn visible_bounds(&self) -> Option<Rect> {
+        self.subtrees
+            .values()
+            .filter(|subtree| subtree.visible())
+            .map(|subtree| subtree.bounding_rect())
+            .reduce(|a, b| a.union(b))
+    }
+
+    /// Place visible subtrees so each one is centered above its visible child
+    /// subtrees, subtrees of the same depth share a row.
+    ///
+    /// Only invalidated subtrees are laid out again and nothing is done if
+    /// there are none.
+    pub(crate) fn update_layout(&self) {
+        let mut changed = false;
+        for subtree in self.subtrees.values() {
+            let valid = std::mem::replace(&mut subtree.ui_state.borrow_mut().layout_valid, true);
+            if !valid {
+                changed = true;
+                // Hidden subtrees are invalidated once shown
+                if subtree.visible() {
+                    subtree.update_base_dimensions();
+                }
+            }
+        }
+        if !changed {
+            return;
+        }
+
+        let visible: Vec<(&Path, &Subtree)> = self
+            .subtrees
+            .iter()
+            .filter(|(_, subtree)| subtree.visible())
+            .collect();
+
+        // The nearest visible ancestor is a parent in the layout
+        let mut children: BTreeMap<Option<&Path>, Vec<&Path>> = BTreeMap::new();
+        for (path, _) in visible.iter() {
+            let parent = (0..path.len()).rev().find_map(|depth| {
+                self.subtrees
+                    .get_key_value(&Path::from(path[0..depth].to_vec()))
+                    .filter(|(_, subtree)| subtree.visible())
+                    .map(|(parent_path, _)| parent_path)
+            });
+            children.entry(parent).or_default().push(path);
+        }
+
+        let mut rows_heights = Vec::new();
+        let mut placements = Vec::new();
+        let roots = children.get(&None).cloned().unwrap_or_default();
+        let branches: Vec<_> = roots
+            .into_iter()
+            .map(|root| self.layout_subtrees(root, 0, &children, &mut rows_heights))
+            .collect();
+        let (offsets, _) =
+            place_siblings(branches.iter().map(|(_, contour)| contour), SUBTREES_GAP);
+        for ((subtrees, _), offset) in branches.into_iter().zip(offsets) {
+            placements.extend(
+                subtrees
+                    .into_iter()
+                    .map(|(path, x, depth)| (path, x + offset, depth)),
+            );
+        }
+
+        let mut rows_y = vec![ORIGIN.y];
+        for height in rows_heights.iter() {
+            rows_y.push(rows_y.last().expect("not empty") + height + LEVELS_GAP);
+        }
+        for (path, x, depth) in placements {
+            self.subtrees[path].ui_state.borrow_mut().position =
+                Pos2::new(ORIGIN.x + x, rows_y[depth]);
+        }
+    }
+
+    /// Lay out a subtree with its visible descendants, returns their
+    /// horizontal offsets relative to the subtree and depths.
+    fn layout_subtrees<'a>(
+        &self,
+        path: &'a Path,
+        depth: usize,
+        children: &BTreeMap<Option<&Path>, Vec<&'a Path>>,
+        rows_heights: &mut Vec<f32>,
+    ) -> (Vec<(&'a Path, f32, usize)>, Contour) {
+        let subtree = &self.subtrees[path];
+        if rows_heights.len() <= depth {
+            rows_heights.push(0.);
+        }
+        rows_heights[depth] = rows_heights[depth].max(subtree.height());
+
+        let branches: Vec<_> = children
+            .get(&Some(path))
+            .into_iter()
+            .flatten()
+            .map(|child| self.layout_subtrees(child, depth + 1, children, rows_heights))
+            .collect();
+        let (offsets, children_contour) =
+            place_siblings(branches.iter().map(|(_, contour)| contour), SUBTREES_GAP);
+
+        let mut subtrees = vec![(path, 0., depth)];
+        for ((branch, _), offset) in branches.into_iter().zip(offsets) {
+            subtrees.extend(
+                branch
+                    .into_iter()
+                    .map(|(path, x, depth)| (path, x + offset, depth)),
+            );
+        }
         (
-            leafs as f32 * NODE_WIDTH,
-            levels as f32 * NODE_HEIGHT,
-            levels,
-            leafs,
+            subtrees,
+            Contour::new(subtree.width()).with_children(children_contour),
         )
-    } else {
-        (0., 0., 0, 0)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::model::Node;
+
+    #[test]
+    fn siblings_are_packed_by_contours() {
+        // A wide branch on the left and a narrow one on the right share only the
+        // top level, so they are placed one node width apart.
+        let wide = Contour::new(NODE_WIDTH).with_children(Contour(vec![(-300., 300.)]));
+        let narrow = Contour::new(NODE_WIDTH);
+        let (offsets, contour) = place_siblings([&wide, &narrow], 0.);
+        assert_eq!(offsets, vec![-NODE_WIDTH / 2., NODE_WIDTH / 2.]);
+        assert_eq!(contour.0[1], (-375., 225.));
+    }
+
+    #[test]
+    fn single_path_down_stays_narrow() {
+        // A long chain of left children takes a node width per level at most
+        // instead of doubling the width each level.
+        let mut subtree = Subtree::new_root(vec![10]);
+        for key in (1..=10).rev() {
+            let mut node = Node::new_item(vec![]);
+            if key > 1 {
+                node = node.with_left_child(vec![key - 1]);
+            }
+            node.ui_state.get_mut().show_left = true;
+            subtree.insert(vec![key], node);
+        }
+
+        let layout = expanded_subtree_layout(&subtree);
+        assert_eq!(layout.nodes.len(), 10);
+        assert_eq!(layout.height, 10. * NODE_HEIGHT);
+        assert_eq!(layout.width, NODE_WIDTH + 9. * NODE_WIDTH / 2.);
+        assert_eq!(layout.nodes[0].parent, None);
+        assert_eq!(layout.nodes[1].parent, Some(vec![10]));
+    }
 
     #[test]
-    fn test_leaves_level_count() {
-        assert_eq!(leaves_level_count(0), 0);
-        assert_eq!(leaves_level_count(1), 1);
-        assert_eq!(leaves_level_count(2), 2);
-        assert_eq!(leaves_level_count(3), 4);
-        assert_eq!(leaves_level_count(4), 8);
+    fn layout_is_cached_until_invalidated() {
+        let mut tree = Tree::new();
+        tree.set_root(vec![2]);
+        tree.insert(
+            Path::default(),
+            vec![2],
+            Node::new_item(vec![]).with_left_child(vec![1]),
+        );
+        tree.insert(Path::default(), vec![1], Node::new_item(vec![]));
+        let path = Path::default();
+        let root = &tree.subtrees[&path];
+        root.set_expanded();
+        tree.update_layout();
+        assert_eq!(root.expanded_layout().len(), 1);
+
+        // Bypassing invalidation leaves the cached layout intact
+        root.nodes[&vec![2]].ui_state.borrow_mut().show_left = true;
+        tree.update_layout();
+        assert_eq!(root.expanded_layout().len(), 1);
+
+        let key = vec![2];
+        tree.get_subtree(&path)
+            .and_then(|subtree| subtree.get_node(&key))
+            .unwrap()
+            .set_left_visible();
+        tree.update_layout();
+        assert_eq!(root.expanded_layout().len(), 2);
     }
 }
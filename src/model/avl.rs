@@ -0,0 +1,141 @@
+//! AVL heights and balance factors of Merk nodes computed from loaded
+//! branches.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use super::{Key, KeySlice, Subtree};
+
+/// Heights of a node's left and right subtrees, an empty subtree has height
+/// of zero.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Heights {
+    pub(crate) left: u32,
+    pub(crate) right: u32,
+}
+
+impl Heights {
+    pub(crate) fn height(&self) -> u32 {
+        1 + self.left.max(self.right)
+    }
+
+    /// Right subtree height minus left subtree height, as Merk defines it
+    pub(crate) fn balance_factor(&self) -> i64 {
+        self.right as i64 - self.left as i64
+    }
+
+    /// AVL invariant allows subtrees heights to differ by one at most
+    pub(crate) fn is_balanced(&self) -> bool {
+        self.balance_factor().abs() <= 1
+    }
+}
+
+impl Subtree {
+    /// Heights of a node, known only if all of its descendants are loaded.
+    pub(crate) fn node_heights(&self, key: KeySlice) -> Option<Heights> {
+        self.heights
+            .borrow_mut()
+            .get_or_insert_with(|| self.compute_heights())
+            .get(key)
+            .copied()
+    }
+
+    /// Drop computed heights, shall be called on every nodes update.
+    pub(super) fn invalidate_heights(&self) {
+        *self.heights.borrow_mut() = None;
+    }
+
+    fn compute_heights(&self) -> BTreeMap<Key, Heights> {
+        let mut heights = BTreeMap::new();
+        let mut unknown = BTreeSet::new();
+        for key in self.nodes.keys() {
+            self.height_of(key, &mut heights, &mut unknown, &mut BTreeSet::new());
+        }
+        heights
+    }
+
+    /// Height of a node's branch, `None` if it's not fully loaded or has a
+    /// cycle.
+    fn height_of<'a>(
+        &'a self,
+        key: &'a Key,
+        heights: &mut BTreeMap<Key, Heights>,
+        unknown: &mut BTreeSet<&'a Key>,
+        visiting: &mut BTreeSet<&'a Key>,
+    ) -> Option<u32> {
+        if let Some(h) = heights.get(key) {
+            return Some(h.height());
+        }
+        let node = self.nodes.get(key)?;
+        if unknown.contains(key) || !visiting.insert(key) {
+            return None;
+        }
+
+        let mut child_height = |child: &'a Option<Key>| match child {
+            Some(child_key) => self.height_of(child_key, heights, unknown, visiting),
+            None => Some(0),
+        };
+        let result = child_height(&node.left_child)
+            .zip(child_height(&node.right_child))
+            .map(|(left, right)| Heights { left, right });
+
+        visiting.remove(key);
+        match result {
+            Some(h) => {
+                heights.insert(key.clone(), h);
+                Some(h.height())
+            }
+            None => {
+                unknown.insert(key);
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Node;
+
+    #[test]
+    fn heights_of_loaded_branches() {
+        // c
+        // ├── b
+        // │   └── a
+        // └── e
+        //     └── f (not loaded yet)
+        let mut subtree = Subtree::new_root(b"c".to_vec());
+        subtree.insert(
+            b"c".to_vec(),
+            Node::new_item(vec![])
+                .with_left_child(b"b".to_vec())
+                .with_right_child(b"e".to_vec()),
+        );
+        subtree.insert(
+            b"b".to_vec(),
+            Node::new_item(vec![]).with_left_child(b"a".to_vec()),
+        );
+        subtree.insert(b"a".to_vec(), Node::new_item(vec![]));
+        subtree.insert(
+            b"e".to_vec(),
+            Node::new_item(vec![]).with_right_child(b"f".to_vec()),
+        );
+
+        assert_eq!(
+            subtree.node_heights(b"a"),
+            Some(Heights { left: 0, right: 0 })
+        );
+        let b = subtree.node_heights(b"b").unwrap();
+        assert_eq!(b.height(), 2);
+        assert_eq!(b.balance_factor(), -1);
+        assert!(b.is_balanced());
+        assert_eq!(subtree.node_heights(b"e"), None);
+        assert_eq!(subtree.node_heights(b"c"), None);
+
+        subtree.insert(b"f".to_vec(), Node::new_item(vec![]));
+        assert_eq!(
+            subtree.node_heights(b"c"),
+            Some(Heights { left: 2, right: 2 })
+        );
+    }
+}
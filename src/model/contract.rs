@@ -0,0 +1,868 @@
+//! Dash Platform data contracts support: documents and index keys decoding.
+//!
+//! Documents of a contract are stored under
+//! `[DOCUMENTS_ROOT, contract_id, 1, document_type]`, where subtree `[0]`
+//! holds serialized documents by their ids and the rest are index subtrees
+//! that alternate property names and encoded property values.
+
+use std::{collections::BTreeMap, fmt};
+
+use serde_json::Value as JsonValue;
+
+use super::Path;
+
+const DOCUMENTS_ROOT: &[u8] = &[64];
+const DOCUMENTS_PREFIX: &[u8] = &[1];
+const PRIMARY_KEY_TREE: &[u8] = &[0];
+const IDENTIFIER_LENGTH: usize = 32;
+
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum ContractError {
+    #[error("invalid JSON: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("bad data contract: {0}")]
+    BadSchema(String),
+}
+
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub(crate) enum DecodeError {
+    #[error("unexpected end of data")]
+    UnexpectedEnd,
+    #[error("varint overflow")]
+    VarintOverflow,
+    #[error("invalid UTF-8 string")]
+    BadString,
+    #[error("{0} trailing bytes left")]
+    TrailingBytes(usize),
+    #[error("{0} bytes integer doesn't fit into 128 bits")]
+    IntegerTooLong(usize),
+}
+
+/// Integer representation chosen by a contract for an `integer` property.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct IntegerType {
+    pub(crate) size: usize,
+    pub(crate) signed: bool,
+}
+
+impl IntegerType {
+    const I64: IntegerType = IntegerType {
+        size: 8,
+        signed: true,
+    };
+
+    fn for_bounds(minimum: Option<i64>, maximum: Option<i64>) -> Self {
+        let (Some(min), Some(max)) = (minimum, maximum) else {
+            return Self::I64;
+        };
+        let size = if min >= 0 {
+            match max {
+                m if m <= u8::MAX as i64 => 1,
+                m if m <= u16::MAX as i64 => 2,
+                m if m <= u32::MAX as i64 => 4,
+                _ => 8,
+            }
+        } else {
+            match (min, max) {
+                (min, max) if min >= i8::MIN as i64 && max <= i8::MAX as i64 => 1,
+                (min, max) if min >= i16::MIN as i64 && max <= i16::MAX as i64 => 2,
+                (min, max) if min >= i32::MIN as i64 && max <= i32::MAX as i64 => 4,
+                _ => 8,
+            }
+        };
+        IntegerType {
+            size,
+            signed: min < 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum PropertyType {
+    String,
+    Integer(IntegerType),
+    Number,
+    Boolean,
+    /// Byte array, fixed size ones are stored without length prefix
+    ByteArray {
+        size: Option<usize>,
+    },
+    Identifier,
+    /// Timestamp in milliseconds
+    Date,
+    Object(Vec<DocumentProperty>),
+    Array(Box<PropertyType>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct DocumentProperty {
+    pub(crate) name: String,
+    pub(crate) property_type: PropertyType,
+    pub(crate) required: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Index {
+    pub(crate) name: String,
+    pub(crate) properties: Vec<String>,
+    pub(crate) unique: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct DocumentType {
+    pub(crate) name: String,
+    /// User defined properties in serialization order
+    pub(crate) properties: Vec<DocumentProperty>,
+    pub(crate) indices: Vec<Index>,
+    pub(crate) requires_revision: bool,
+}
+
+impl DocumentType {
+    /// Type of a property that can be used in an index, including system ones.
+    pub(crate) fn index_property_type(&self, name: &str) -> Option<PropertyType> {
+        match name {
+            "$id" | "$ownerId" | "$creatorId" => Some(PropertyType::Identifier),
+            "$createdAt" | "$updatedAt" | "$transferredAt" => Some(PropertyType::Date),
+            "$createdAtBlockHeight" | "$updatedAtBlockHeight" | "$transferredAtBlockHeight" => {
+                Some(PropertyType::Integer(IntegerType {
+                    size: 8,
+                    signed: false,
+                }))
+            }
+            "$createdAtCoreBlockHeight"
+            | "$updatedAtCoreBlockHeight"
+            | "$transferredAtCoreBlockHeight" => Some(PropertyType::Integer(IntegerType {
+                size: 4,
+                signed: false,
+            })),
+            _ => find_property(&self.properties, name).map(|p| p.property_type.clone()),
+        }
+    }
+}
+
+/// Lookup a property, nested ones are addressed with dots.
+fn find_property<'a>(
+    properties: &'a [DocumentProperty],
+    name: &str,
+) -> Option<&'a DocumentProperty> {
+    let (head, tail) = match name.split_once('.') {
+        Some((head, tail)) => (head, Some(tail)),
+        None => (name, None),
+    };
+    let property = properties.iter().find(|p| p.name == head)?;
+    match (tail, &property.property_type) {
+        (None, _) => Some(property),
+        (Some(tail), PropertyType::Object(inner)) => find_property(inner, tail),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct DataContract {
+    pub(crate) id: Vec<u8>,
+    pub(crate) document_types: BTreeMap<String, DocumentType>,
+}
+
+impl DataContract {
+    pub(crate) fn from_json(json: &str) -> Result<Self, ContractError> {
+        let value: JsonValue = serde_json::from_str(json)?;
+        let id = value
+            .get("id")
+            .or_else(|| value.get("$id"))
+            .ok_or_else(|| ContractError::BadSchema("no contract id".to_owned()))
+            .and_then(parse_identifier)?;
+        let sized_integer_types = value
+            .get("config")
+            .and_then(|c| c.get("sizedIntegerTypes"))
+            .and_then(JsonValue::as_bool)
+            .unwrap_or_else(|| {
+                value.get("$format_version").and_then(JsonValue::as_str) == Some("1")
+            });
+
+        let schemas = value
+            .get("documentSchemas")
+            .or_else(|| value.get("documents"))
+            .and_then(JsonValue::as_object)
+            .ok_or_else(|| ContractError::BadSchema("no document schemas".to_owned()))?;
+
+        let document_types = schemas
+            .iter()
+            .map(|(name, schema)| {
+                parse_document_type(name, schema, sized_integer_types).map(|t| (name.clone(), t))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(DataContract { id, document_types })
+    }
+
+    pub(crate) fn id_string(&self) -> String {
+        bs58::encode(&self.id).into_string()
+    }
+}
+
+fn parse_identifier(value: &JsonValue) -> Result<Vec<u8>, ContractError> {
+    let bytes = match value {
+        JsonValue::String(s) => bs58::decode(s)
+            .into_vec()
+            .map_err(|e| ContractError::BadSchema(format!("bad base58 identifier: {e}")))?,
+        JsonValue::Array(items) => items
+            .iter()
+            .map(|b| b.as_u64().and_then(|b| u8::try_from(b).ok()))
+            .collect::<Option<_>>()
+            .ok_or_else(|| ContractError::BadSchema("bad identifier bytes".to_owned()))?,
+        _ => return Err(ContractError::BadSchema("bad identifier".to_owned())),
+    };
+    if bytes.len() != IDENTIFIER_LENGTH {
+        return Err(ContractError::BadSchema(format!(
+            "identifier must be {IDENTIFIER_LENGTH} bytes"
+        )));
+    }
+    Ok(bytes)
+}
+
+fn parse_document_type(
+    name: &str,
+    schema: &JsonValue,
+    sized_integer_types: bool,
+) -> Result<DocumentType, ContractError> {
+    let properties = parse_properties(schema, sized_integer_types)?;
+    let indices = schema
+        .get("indices")
+        .and_then(JsonValue::as_array)
+        .into_iter()
+        .flatten()
+        .map(|index| {
+            let properties = index
+                .get("properties")
+                .and_then(JsonValue::as_array)
+                .ok_or_else(|| {
+                    ContractError::BadSchema(format!("index without properties in {name}"))
+                })?
+                .iter()
+                .filter_map(|p| p.as_object().and_then(|p| p.keys().next().cloned()))
+                .collect();
+            Ok(Index {
+                name: index
+                    .get("name")
+                    .and_then(JsonValue::as_str)
+                    .unwrap_or_default()
+                    .to_owned(),
+                properties,
+                unique: index
+                    .get("unique")
+                    .and_then(JsonValue::as_bool)
+                    .unwrap_or_default(),
+            })
+        })
+        .collect::<Result<_, ContractError>>()?;
+    let documents_mutable = schema
+        .get("documentsMutable")
+        .and_then(JsonValue::as_bool)
+        .unwrap_or(true);
+    let transferable = schema
+        .get("transferable")
+        .and_then(JsonValue::as_u64)
+        .unwrap_or_default()
+        > 0;
+
+    Ok(DocumentType {
+        name: name.to_owned(),
+        properties,
+        indices,
+        requires_revision: documents_mutable || transferable,
+    })
+}
+
+fn parse_properties(
+    schema: &JsonValue,
+    sized_integer_types: bool,
+) -> Result<Vec<DocumentProperty>, ContractError> {
+    let required: Vec<&str> = schema
+        .get("required")
+        .and_then(JsonValue::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(JsonValue::as_str)
+        .collect();
+    let Some(properties) = schema.get("properties").and_then(JsonValue::as_object) else {
+        return Ok(Vec::new());
+    };
+
+    let mut positioned: Vec<(Option<u64>, DocumentProperty)> = properties
+        .iter()
+        .map(|(name, property)| {
+            Ok((
+                property.get("position").and_then(JsonValue::as_u64),
+                DocumentProperty {
+                    name: name.clone(),
+                    property_type: parse_property_type(property, sized_integer_types)?,
+                    required: required.contains(&name.as_str()),
+                },
+            ))
+        })
+        .collect::<Result<_, ContractError>>()?;
+    // Properties are serialized by position, contracts without positions use
+    // alphabetical order
+    positioned.sort_by(|(pos_a, a), (pos_b, b)| {
+        pos_a
+            .unwrap_or(u64::MAX)
+            .cmp(&pos_b.unwrap_or(u64::MAX))
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    Ok(positioned.into_iter().map(|(_, p)| p).collect())
+}
+
+fn parse_property_type(
+    property: &JsonValue,
+    sized_integer_types: bool,
+) -> Result<PropertyType, ContractError> {
+    let type_name = property
+        .get("type")
+        .and_then(JsonValue::as_str)
+        .ok_or_else(|| ContractError::BadSchema("property without type".to_owned()))?;
+    Ok(match type_name {
+        "string" => PropertyType::String,
+        "integer" if sized_integer_types => PropertyType::Integer(IntegerType::for_bounds(
+            property.get("minimum").and_then(JsonValue::as_i64),
+            property.get("maximum").and_then(JsonValue::as_i64),
+        )),
+        "integer" => PropertyType::Integer(IntegerType::I64),
+        "number" => PropertyType::Number,
+        "boolean" => PropertyType::Boolean,
+        "date" => PropertyType::Date,
+        "object" => PropertyType::Object(parse_properties(property, sized_integer_types)?),
+        "array" if property.get("byteArray").and_then(JsonValue::as_bool) == Some(true) => {
+            let min = property.get("minItems").and_then(JsonValue::as_u64);
+            let max = property.get("maxItems").and_then(JsonValue::as_u64);
+            if property.get("contentMediaType").and_then(JsonValue::as_str)
+                == Some("application/x.dash.dpp.identifier")
+            {
+                PropertyType::Identifier
+            } else {
+                PropertyType::ByteArray {
+                    size: min.filter(|min| Some(*min) == max).map(|s| s as usize),
+                }
+            }
+        }
+        "array" => PropertyType::Array(Box::new(
+            property
+                .get("items")
+                .map(|items| parse_property_type(items, sized_integer_types))
+                .transpose()?
+                .unwrap_or(PropertyType::String),
+        )),
+        other => {
+            return Err(ContractError::BadSchema(format!(
+                "unknown property type {other}"
+            )))
+        }
+    })
+}
+
+/// Decoded document or index value
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Value {
+    Null,
+    Bool(bool),
+    Integer(i128),
+    Float(f64),
+    String(String),
+    Bytes(Vec<u8>),
+    Identifier(Vec<u8>),
+    Object(Vec<(String, Value)>),
+    Array(Vec<Value>),
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Null => write!(f, "null"),
+            Value::Bool(b) => write!(f, "{b}"),
+            Value::Integer(i) => write!(f, "{i}"),
+            Value::Float(x) => write!(f, "{x}"),
+            Value::String(s) => write!(f, "{s:?}"),
+            Value::Bytes(b) => write!(f, "0x{}", hex::encode(b)),
+            Value::Identifier(id) => write!(f, "{}", bs58::encode(id).into_string()),
+            Value::Object(fields) => {
+                write!(f, "{{")?;
+                for (i, (name, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{name}: {value}")?;
+                }
+                write!(f, "}}")
+            }
+            Value::Array(items) => {
+                write!(f, "[")?;
+                for (i, value) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{value}")?;
+                }
+                write!(f, "]")
+            }
+        }
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], DecodeError> {
+        if self.bytes.len() < n {
+            return Err(DecodeError::UnexpectedEnd);
+        }
+        let (head, tail) = self.bytes.split_at(n);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> Result<u8, DecodeError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], DecodeError> {
+        Ok(self.take(N)?.try_into().expect("length checked"))
+    }
+
+    /// Unsigned LEB128 varint
+    fn varint(&mut self) -> Result<u64, DecodeError> {
+        let mut result = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.u8()?;
+            result |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(result);
+            }
+        }
+        Err(DecodeError::VarintOverflow)
+    }
+
+    fn sized_bytes(&mut self) -> Result<&'a [u8], DecodeError> {
+        let len = self.varint()? as usize;
+        self.take(len)
+    }
+
+    fn integer(&mut self, integer_type: IntegerType) -> Result<i128, DecodeError> {
+        let bytes = self.take(integer_type.size)?;
+        decode_integer(bytes, integer_type.signed).ok_or(DecodeError::IntegerTooLong(bytes.len()))
+    }
+
+    fn value(&mut self, property_type: &PropertyType) -> Result<Value, DecodeError> {
+        Ok(match property_type {
+            PropertyType::String => Value::String(
+                String::from_utf8(self.sized_bytes()?.to_vec())
+                    .map_err(|_| DecodeError::BadString)?,
+            ),
+            PropertyType::Integer(integer_type) => Value::Integer(self.integer(*integer_type)?),
+            PropertyType::Number => Value::Float(f64::from_be_bytes(self.array()?)),
+            PropertyType::Boolean => Value::Bool(self.u8()? != 0),
+            PropertyType::ByteArray { size: Some(size) } => {
+                Value::Bytes(self.take(*size)?.to_vec())
+            }
+            PropertyType::ByteArray { size: None } => Value::Bytes(self.sized_bytes()?.to_vec()),
+            PropertyType::Identifier => Value::Identifier(self.take(IDENTIFIER_LENGTH)?.to_vec()),
+            PropertyType::Date => Value::Integer(u64::from_be_bytes(self.array()?) as i128),
+            PropertyType::Object(properties) => {
+                let mut inner = Reader {
+                    bytes: self.sized_bytes()?,
+                };
+                Value::Object(inner.properties(properties)?)
+            }
+            PropertyType::Array(item_type) => {
+                let len = self.varint()?;
+                Value::Array(
+                    (0..len)
+                        .map(|_| self.value(item_type))
+                        .collect::<Result<_, _>>()?,
+                )
+            }
+        })
+    }
+
+    fn properties(
+        &mut self,
+        properties: &[DocumentProperty],
+    ) -> Result<Vec<(String, Value)>, DecodeError> {
+        properties
+            .iter()
+            .map(|property| {
+                let value = if property.required || self.u8()? != 0 {
+                    self.value(&property.property_type)?
+                } else {
+                    Value::Null
+                };
+                Ok((property.name.clone(), value))
+            })
+            .collect()
+    }
+}
+
+/// Big-endian integer of up to 16 bytes, `None` if there are more.
+fn decode_integer(bytes: &[u8], signed: bool) -> Option<i128> {
+    if bytes.len() > 16 {
+        return None;
+    }
+    let mut buf = if signed && bytes.first().map(|b| b & 0x80 != 0).unwrap_or_default() {
+        [0xff; 16]
+    } else {
+        [0; 16]
+    };
+    buf[16 - bytes.len()..].copy_from_slice(bytes);
+    Some(i128::from_be_bytes(buf))
+}
+
+/// Timestamp fields of a document, in order of their bits in the document's
+/// flags, with their sizes in bytes.
+const TIME_FIELDS: [(&str, usize); 9] = [
+    ("$createdAt", 8),
+    ("$updatedAt", 8),
+    ("$transferredAt", 8),
+    ("$createdAtBlockHeight", 8),
+    ("$updatedAtBlockHeight", 8),
+    ("$transferredAtBlockHeight", 8),
+    ("$createdAtCoreBlockHeight", 4),
+    ("$updatedAtCoreBlockHeight", 4),
+    ("$transferredAtCoreBlockHeight", 4),
+];
+
+/// Decode a serialized document into a list of fields, system fields
+/// first.
+pub(crate) fn decode_document(
+    document_type: &DocumentType,
+    bytes: &[u8],
+) -> Result<Vec<(String, Value)>, DecodeError> {
+    let mut reader = Reader { bytes };
+    let mut fields = Vec::new();
+
+    fields.push((
+        "$version".to_owned(),
+        Value::Integer(reader.varint()? as i128),
+    ));
+    fields.push((
+        "$id".to_owned(),
+        Value::Identifier(reader.take(IDENTIFIER_LENGTH)?.to_vec()),
+    ));
+    fields.push((
+        "$ownerId".to_owned(),
+        Value::Identifier(reader.take(IDENTIFIER_LENGTH)?.to_vec()),
+    ));
+    if document_type.requires_revision {
+        fields.push((
+            "$revision".to_owned(),
+            Value::Integer(reader.varint()? as i128),
+        ));
+    }
+
+    let time_flags = u16::from_be_bytes(reader.array()?);
+    for (bit, (name, size)) in TIME_FIELDS.iter().enumerate() {
+        if time_flags & (1 << bit) != 0 {
+            let bytes = reader.take(*size)?;
+            let value =
+                decode_integer(bytes, false).ok_or(DecodeError::IntegerTooLong(bytes.len()))?;
+            fields.push(((*name).to_owned(), Value::Integer(value)));
+        }
+    }
+
+    fields.extend(reader.properties(&document_type.properties)?);
+
+    if !reader.bytes.is_empty() {
+        return Err(DecodeError::TrailingBytes(reader.bytes.len()));
+    }
+
+    Ok(fields)
+}
+
+/// Decode bytes of a GroveDB key used for a document index.
+pub(crate) fn decode_index_key(property_type: &PropertyType, bytes: &[u8]) -> Value {
+    match property_type {
+        PropertyType::String => Value::String(String::from_utf8_lossy(bytes).into_owned()),
+        PropertyType::Integer(IntegerType { signed: true, .. }) => {
+            let mut flipped = bytes.to_vec();
+            if let Some(first) = flipped.first_mut() {
+                *first ^= 0x80;
+            }
+            decode_integer(&flipped, true)
+                .map(Value::Integer)
+                .unwrap_or_else(|| Value::Bytes(bytes.to_vec()))
+        }
+        PropertyType::Integer(IntegerType { signed: false, .. }) | PropertyType::Date => {
+            decode_integer(bytes, false)
+                .map(Value::Integer)
+                .unwrap_or_else(|| Value::Bytes(bytes.to_vec()))
+        }
+        PropertyType::Number => {
+            let Ok(mut array) = <[u8; 8]>::try_from(bytes) else {
+                return Value::Bytes(bytes.to_vec());
+            };
+            // Positive numbers have the sign bit flipped, negative have all bits
+            // flipped
+            if array[0] & 0x80 != 0 {
+                array[0] ^= 0x80;
+            } else {
+                array.iter_mut().for_each(|b| *b = !*b);
+            }
+            Value::Float(f64::from_be_bytes(array))
+        }
+        PropertyType::Boolean => Value::Bool(bytes.first().copied().unwrap_or_default() != 0),
+        PropertyType::Identifier => Value::Identifier(bytes.to_vec()),
+        _ => Value::Bytes(bytes.to_vec()),
+    }
+}
+
+/// Meaning of a subtree from the documents part of GroveDB.
+#[derive(Debug, PartialEq)]
+pub(crate) enum DocumentsSubtree<'c> {
+    /// Root subtree of a document type, keys are first properties of indices
+    DocumentType(&'c DocumentType),
+    /// Subtree of serialized documents by their ids
+    PrimaryKey(&'c DocumentType),
+    /// Subtree with keys being values of the property
+    IndexProperty {
+        document_type: &'c DocumentType,
+        property: String,
+    },
+    /// Subtree under a property value, keys are next index properties' names
+    IndexValue {
+        document_type: &'c DocumentType,
+        property: String,
+        value: Value,
+    },
+    /// Subtree with references to documents by their ids
+    IndexTerminal(&'c DocumentType),
+}
+
+impl<'c> DocumentsSubtree<'c> {
+    /// Human readable meaning of a subtree.
+    pub(crate) fn label(&self) -> String {
+        match self {
+            DocumentsSubtree::DocumentType(t) => format!("Documents: {}", t.name),
+            DocumentsSubtree::PrimaryKey(t) => format!("{} documents by id", t.name),
+            DocumentsSubtree::IndexProperty {
+                document_type,
+                property,
+            } => format!("{} index on {property}", document_type.name),
+            DocumentsSubtree::IndexValue {
+                document_type,
+                property,
+                value,
+            } => format!("{} where {property} = {value}", document_type.name),
+            DocumentsSubtree::IndexTerminal(t) => format!("{} documents ids", t.name),
+        }
+    }
+
+    /// Decode a key of the subtree according to the subtree meaning.
+    pub(crate) fn decode_key(&self, key: &[u8]) -> Option<Value> {
+        match self {
+            DocumentsSubtree::PrimaryKey(_) | DocumentsSubtree::IndexTerminal(_) => {
+                (key.len() == IDENTIFIER_LENGTH).then(|| Value::Identifier(key.to_vec()))
+            }
+            DocumentsSubtree::IndexProperty {
+                document_type,
+                property,
+            } => document_type
+                .index_property_type(property)
+                .map(|t| decode_index_key(&t, key)),
+            DocumentsSubtree::DocumentType(_) | DocumentsSubtree::IndexValue { .. } => None,
+        }
+    }
+}
+
+/// Data contracts loaded by user
+#[derive(Debug, Default)]
+pub(crate) struct Contracts {
+    contracts: BTreeMap<Vec<u8>, DataContract>,
+}
+
+impl Contracts {
+    pub(crate) fn load_json(&mut self, json: &str) -> Result<(), ContractError> {
+        let contract = DataContract::from_json(json)?;
+        self.contracts.insert(contract.id.clone(), contract);
+        Ok(())
+    }
+
+    pub(crate) fn remove(&mut self, id: &[u8]) {
+        self.contracts.remove(id);
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &DataContract> {
+        self.contracts.values()
+    }
+
+    /// Find what a subtree under the path means for a loaded contract.
+    pub(crate) fn documents_subtree(&self, path: &Path) -> Option<DocumentsSubtree> {
+        let [root, contract_id, prefix, document_type, rest @ ..] = path.as_slice() else {
+            return None;
+        };
+        if root != DOCUMENTS_ROOT || prefix != DOCUMENTS_PREFIX {
+            return None;
+        }
+        let document_type = self
+            .contracts
+            .get(contract_id)?
+            .document_types
+            .get(std::str::from_utf8(document_type).ok()?)?;
+
+        Some(match rest {
+            [] => DocumentsSubtree::DocumentType(document_type),
+            [key] if key == PRIMARY_KEY_TREE => DocumentsSubtree::PrimaryKey(document_type),
+            // Terminals follow complete property and value pairs, otherwise
+            // the segment is a value that happens to be the same byte
+            [.., last] if last == PRIMARY_KEY_TREE && rest.len() % 2 == 1 => {
+                DocumentsSubtree::IndexTerminal(document_type)
+            }
+            [.., property, value] if rest.len() % 2 == 0 => {
+                let property = String::from_utf8_lossy(property).into_owned();
+                let value = document_type
+                    .index_property_type(&property)
+                    .map(|t| decode_index_key(&t, value))
+                    .unwrap_or_else(|| Value::Bytes(value.clone()));
+                DocumentsSubtree::IndexValue {
+                    document_type,
+                    property,
+                    value,
+                }
+            }
+            [.., property] => DocumentsSubtree::IndexProperty {
+                document_type,
+                property: String::from_utf8_lossy(property).into_owned(),
+            },
+        })
+    }
+
+    /// Decode an item if it's a document of a loaded contract.
+    pub(crate) fn decode_item(
+        &self,
+        path: &Path,
+        value: &[u8],
+    ) -> Option<Result<Vec<(String, Value)>, DecodeError>> {
+        match self.documents_subtree(path)? {
+            DocumentsSubtree::PrimaryKey(document_type) => {
+                Some(decode_document(document_type, value))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONTRACT_ID: [u8; 32] = [7; 32];
+
+    fn contract_json() -> String {
+        format!(
+            r#"{{
+                "id": "{}",
+                "documentSchemas": {{
+                    "note": {{
+                        "type": "object",
+                        "documentsMutable": false,
+                        "indices": [
+                            {{
+                                "name": "byScore",
+                                "properties": [{{"score": "asc"}}, {{"$ownerId": "asc"}}]
+                            }}
+                        ],
+                        "properties": {{
+                            "text": {{"type": "string", "position": 0}},
+                            "score": {{"type": "integer", "position": 1}},
+                            "tag": {{"type": "string", "position": 2}}
+                        }},
+                        "required": ["text", "score"]
+                    }}
+                }}
+            }}"#,
+            bs58::encode(CONTRACT_ID).into_string()
+        )
+    }
+
+    fn contracts() -> Contracts {
+        let mut contracts = Contracts::default();
+        contracts.load_json(&contract_json()).unwrap();
+        contracts
+    }
+
+    fn documents_path(rest: &[&[u8]]) -> Path {
+        [DOCUMENTS_ROOT, &CONTRACT_ID, DOCUMENTS_PREFIX, b"note"]
+            .into_iter()
+            .chain(rest.iter().copied())
+            .map(|s| s.to_vec())
+            .collect::<Vec<_>>()
+            .into()
+    }
+
+    #[test]
+    fn decode_document_fields() {
+        let contracts = contracts();
+
+        let mut bytes = vec![0];
+        bytes.extend([1; 32]);
+        bytes.extend([2; 32]);
+        bytes.extend(1u16.to_be_bytes());
+        bytes.extend(1000u64.to_be_bytes());
+        bytes.push(5);
+        bytes.extend(b"hello");
+        bytes.extend((-3i64).to_be_bytes());
+        bytes.push(0);
+
+        let fields = contracts
+            .decode_item(&documents_path(&[PRIMARY_KEY_TREE]), &bytes)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(
+            fields,
+            vec![
+                ("$version".to_owned(), Value::Integer(0)),
+                ("$id".to_owned(), Value::Identifier(vec![1; 32])),
+                ("$ownerId".to_owned(), Value::Identifier(vec![2; 32])),
+                ("$createdAt".to_owned(), Value::Integer(1000)),
+                ("text".to_owned(), Value::String("hello".to_owned())),
+                ("score".to_owned(), Value::Integer(-3)),
+                ("tag".to_owned(), Value::Null),
+            ]
+        );
+    }
+
+    #[test]
+    fn decode_index_subtrees() {
+        let contracts = contracts();
+
+        let index = contracts
+            .documents_subtree(&documents_path(&[b"score"]))
+            .unwrap();
+        assert_eq!(index.label(), "note index on score");
+
+        let mut key = 42i64.to_be_bytes();
+        key[0] ^= 0x80;
+        assert_eq!(index.decode_key(&key), Some(Value::Integer(42)));
+        // Document ids under an integer index don't fit an integer
+        assert_eq!(index.decode_key(&[5; 32]), Some(Value::Bytes(vec![5; 32])));
+
+        let value = contracts
+            .documents_subtree(&documents_path(&[b"score", &key]))
+            .unwrap();
+        assert_eq!(value.label(), "note where score = 42");
+
+        assert!(contracts
+            .documents_subtree(&documents_path(&[
+                b"score",
+                &key,
+                b"$ownerId",
+                &[3; 32],
+                &[0]
+            ]))
+            .is_some_and(|s| matches!(s, DocumentsSubtree::IndexTerminal(_))));
+
+        // Zero byte as an index value is not a terminal
+        assert!(contracts
+            .documents_subtree(&documents_path(&[b"score", &[0]]))
+            .is_some_and(|s| matches!(s, DocumentsSubtree::IndexValue { .. })));
+    }
+}
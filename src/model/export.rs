@@ -0,0 +1,258 @@
+//! Dump of loaded subtree data to load into notebooks and spreadsheets.
+
+use std::cell::RefMut;
+
+use serde::Serialize;
+
+use super::{Element, Node, Path, Subtree, Tree};
+use crate::ui::DisplayVariant;
+
+const CSV_HEADER: &str = "path,key,element_type,value,left_child,right_child";
+
+#[derive(Debug, Clone, Copy, PartialEq, strum::EnumIter, strum::AsRefStr)]
+pub(crate) enum DataFormat {
+    #[strum(serialize = "JSON")]
+    Json,
+    #[strum(serialize = "CSV")]
+    Csv,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub(crate) enum ExportedValue {
+    Text(String),
+    Number(i64),
+}
+
+impl ExportedValue {
+    fn to_text(&self) -> String {
+        match self {
+            ExportedValue::Text(text) => text.clone(),
+            ExportedValue::Number(number) => number.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub(crate) struct ExportedChildren {
+    pub(crate) left: Option<String>,
+    pub(crate) right: Option<String>,
+}
+
+/// Loaded node with bytes decoded by the display variants chosen for them
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub(crate) struct ExportedNode {
+    pub(crate) path: Vec<String>,
+    pub(crate) key: String,
+    pub(crate) element_type: String,
+    /// `None` for subtrees, their contents are exported as separate nodes
+    pub(crate) value: Option<ExportedValue>,
+    /// Keys of the node's children in the Merk tree
+    pub(crate) children: ExportedChildren,
+}
+
+/// Bytes in the display variant, unlike canvas labels nothing is shortened
+fn decoded(bytes: &[u8], display_variant: DisplayVariant) -> String {
+    match display_variant {
+        DisplayVariant::U8 => format!("{bytes:?}"),
+        DisplayVariant::String => String::from_utf8_lossy(bytes).into_owned(),
+        DisplayVariant::Hex => hex::encode(bytes),
+    }
+}
+
+fn decoded_path(path: &Path, display_variant: DisplayVariant) -> Vec<String> {
+    path.iter()
+        .map(|segment| decoded(segment, display_variant))
+        .collect()
+}
+
+/// Path segments joined with `/`, with `%` and `/` in segments
+/// percent-encoded to keep the joined path unambiguous.
+fn joined_path<'a>(segments: impl IntoIterator<Item = &'a String>) -> String {
+    segments
+        .into_iter()
+        .map(|segment| segment.replace('%', "%25").replace('/', "%2F"))
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn exported_value(node: &Node) -> Option<ExportedValue> {
+    let display_variant = node.ui_state.borrow().item_display_variant;
+    match &node.element {
+        Element::Item { value } => Some(ExportedValue::Text(decoded(value, display_variant))),
+        Element::SumItem { value } => Some(ExportedValue::Number(*value)),
+        Element::Reference { path, key } => {
+            let mut target = decoded_path(path, display_variant);
+            target.push(decoded(key, display_variant));
+            Some(ExportedValue::Text(joined_path(&target)))
+        }
+        Element::Sumtree { sum, .. } => Some(ExportedValue::Number(*sum)),
+        Element::Subtree { .. } | Element::SubtreePlaceholder => None,
+    }
+}
+
+/// Quote the CSV field if it has separators, quotes or line breaks in it.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+impl DataFormat {
+    pub(crate) fn mime(&self) -> &'static str {
+        match self {
+            DataFormat::Json => "application/json",
+            DataFormat::Csv => "text/csv",
+        }
+    }
+
+    pub(crate) fn extension(&self) -> &'static str {
+        match self {
+            DataFormat::Json => "json",
+            DataFormat::Csv => "csv",
+        }
+    }
+
+    /// JSON array of nodes or a CSV table with a row per node, paths are
+    /// joined with `/` in CSV and their segments escaped.
+    pub(crate) fn write(&self, nodes: &[ExportedNode]) -> String {
+        match self {
+            DataFormat::Json => {
+                serde_json::to_string_pretty(nodes).expect("exported nodes are serializable")
+            }
+            DataFormat::Csv => {
+                let mut csv = String::from(CSV_HEADER);
+                csv.push('\n');
+                for node in nodes {
+                    let fields = [
+                        joined_path(&node.path),
+                        node.key.clone(),
+                        node.element_type.clone(),
+                        node.value
+                            .as_ref()
+                            .map(ExportedValue::to_text)
+                            .unwrap_or_default(),
+                        node.children.left.clone().unwrap_or_default(),
+                        node.children.right.clone().unwrap_or_default(),
+                    ];
+                    let row: Vec<_> = fields.iter().map(|field| csv_field(field)).collect();
+                    csv.push_str(&row.join(","));
+                    csv.push('\n');
+                }
+                csv
+            }
+        }
+    }
+}
+
+impl Subtree {
+    /// Export loaded descendant subtrees along with the subtree
+    pub(crate) fn export_descendants_mut(&self) -> RefMut<bool> {
+        RefMut::map(self.ui_state.borrow_mut(), |state| {
+            &mut state.export_descendants
+        })
+    }
+
+    /// Outcome of the last export of the subtree data
+    pub(crate) fn export_status_mut(&self) -> RefMut<Option<Result<String, String>>> {
+        RefMut::map(self.ui_state.borrow_mut(), |state| &mut state.export_status)
+    }
+}
+
+impl Tree {
+    /// Loaded nodes of the subtree, and of its loaded descendant subtrees if
+    /// asked, in path and key order.
+    pub(crate) fn exported_nodes(&self, path: &Path, descendants: bool) -> Vec<ExportedNode> {
+        self.subtrees
+            .iter()
+            .filter(|(subtree_path, _)| {
+                *subtree_path == path || (descendants && subtree_path.starts_with(path))
+            })
+            .flat_map(|(subtree_path, subtree)| {
+                let path = decoded_path(subtree_path, *subtree.path_display_variant_mut());
+                subtree.nodes.iter().map(move |(key, node)| {
+                    let key_display_variant = node.ui_state.borrow().key_display_variant;
+                    // Children keys are shown as their own nodes have them
+                    let child = |child: &Option<Vec<u8>>| {
+                        child.as_ref().map(|child| {
+                            let display_variant = subtree
+                                .nodes
+                                .get(child)
+                                .map(|node| node.ui_state.borrow().key_display_variant)
+                                .unwrap_or(key_display_variant);
+                            decoded(child, display_variant)
+                        })
+                    };
+                    ExportedNode {
+                        path: path.clone(),
+                        key: decoded(key, key_display_variant),
+                        element_type: node.element.as_ref().to_owned(),
+                        value: exported_value(node),
+                        children: ExportedChildren {
+                            left: child(&node.left_child),
+                            right: child(&node.right_child),
+                        },
+                    }
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn export_with_descendants() {
+        let mut tree = Tree::new();
+        tree.set_root(b"a".to_vec());
+        tree.insert(
+            Path::default(),
+            b"a".to_vec(),
+            Node::new_subtree(Some(b"x".to_vec())),
+        );
+        tree.insert(
+            vec![b"a".to_vec()].into(),
+            b"x".to_vec(),
+            Node::new_item(b"one, \"two\"".to_vec()).with_left_child(b"w/v".to_vec()),
+        );
+        tree.insert(
+            vec![b"a".to_vec()].into(),
+            b"w/v".to_vec(),
+            Node::new_subtree(None),
+        );
+        tree.subtrees[&vec![b"a".to_vec()].into()]
+            .nodes
+            .values()
+            .for_each(|node| {
+                let mut state = node.ui_state.borrow_mut();
+                state.key_display_variant = DisplayVariant::String;
+                state.item_display_variant = DisplayVariant::String;
+            });
+        let nested: Path = vec![b"a".to_vec(), b"w/v".to_vec()].into();
+        tree.insert(nested.clone(), b"y".to_vec(), Node::new_sum_item(1));
+        *tree.subtrees[&nested].path_display_variant_mut() = DisplayVariant::String;
+        tree.get_node(&vec![b"a".to_vec()].into(), b"x")
+            .unwrap()
+            .ui_state
+            .borrow_mut()
+            .key_display_variant = DisplayVariant::Hex;
+
+        assert_eq!(tree.exported_nodes(&Path::default(), false).len(), 1);
+        let nodes = tree.exported_nodes(&Path::default(), true);
+        assert_eq!(nodes.len(), 4);
+        assert_eq!(nodes[2].key, "78");
+        assert_eq!(nodes[2].children.left.as_deref(), Some("w/v"));
+        assert_eq!(
+            nodes[2].value,
+            Some(ExportedValue::Text("one, \"two\"".to_owned()))
+        );
+
+        let csv = DataFormat::Csv.write(&nodes);
+        assert!(csv.contains("\n[97],78,Item,\"one, \"\"two\"\"\",w/v,\n"));
+        assert!(csv.ends_with("\na/w%2Fv,[121],SumItem,1,,\n"));
+    }
+}
@@ -0,0 +1,221 @@
+//! Merk hashes computation to verify fetched data locally.
+
+use std::fmt;
+
+use super::{KeySlice, Node, Subtree};
+use crate::ui::bytes_as_hex;
+
+pub(crate) type CryptoHash = [u8; 32];
+
+/// Hash used in place of a missing child
+pub(crate) const NULL_HASH: CryptoHash = [0; 32];
+
+/// Unsigned LEB128 encoding used by Merk for lengths prefixes
+fn encode_varint(mut n: usize, buf: &mut Vec<u8>) {
+    while n >= 0x80 {
+        buf.push((n as u8) | 0x80);
+        n >>= 7;
+    }
+    buf.push(n as u8);
+}
+
+pub(crate) fn value_hash(value: &[u8]) -> CryptoHash {
+    let mut length = Vec::new();
+    encode_varint(value.len(), &mut length);
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&length);
+    hasher.update(value);
+    hasher.finalize().into()
+}
+
+pub(crate) fn kv_digest_to_kv_hash(key: KeySlice, value_hash: &CryptoHash) -> CryptoHash {
+    let mut length = Vec::new();
+    encode_varint(key.len(), &mut length);
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&length);
+    hasher.update(key);
+    hasher.update(value_hash);
+    hasher.finalize().into()
+}
+
+pub(crate) fn node_hash(kv_hash: &CryptoHash, left: &CryptoHash, right: &CryptoHash) -> CryptoHash {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(kv_hash);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+/// Hash that binds a subtree element value hash with the subtree's root hash
+pub(crate) fn combine_hash(a: &CryptoHash, b: &CryptoHash) -> CryptoHash {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(a);
+    hasher.update(b);
+    hasher.finalize().into()
+}
+
+/// Disagreement between hashes received from GroveDB and ones computed
+/// locally.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum HashMismatch {
+    KvHash {
+        received: CryptoHash,
+        computed: CryptoHash,
+    },
+    LeftChild {
+        received: CryptoHash,
+        computed: CryptoHash,
+    },
+    RightChild {
+        received: CryptoHash,
+        computed: CryptoHash,
+    },
+}
+
+impl fmt::Display for HashMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (what, received, computed) = match self {
+            HashMismatch::KvHash { received, computed } => ("KV hash", received, computed),
+            HashMismatch::LeftChild { received, computed } => {
+                ("Left child hash", received, computed)
+            }
+            HashMismatch::RightChild { received, computed } => {
+                ("Right child hash", received, computed)
+            }
+        };
+        write!(
+            f,
+            "{what}: received {}, computed {}",
+            bytes_as_hex(received),
+            bytes_as_hex(computed)
+        )
+    }
+}
+
+impl Node {
+    /// Merk node hash out of received KV hash and children hashes, `None` if
+    /// no hashes were received for the node. Ghost nodes know their hash
+    /// already.
+    pub(crate) fn node_hash(&self) -> Option<CryptoHash> {
+        if self.ghost.is_some() {
+            return self.ghost;
+        }
+        let child_hash = |child: &Option<_>, hash: Option<CryptoHash>| match child {
+            Some(_) => hash,
+            None => Some(NULL_HASH),
+        };
+        Some(node_hash(
+            self.kv_digest_hash.as_ref()?,
+            &child_hash(&self.left_child, self.left_merk_hash)?,
+            &child_hash(&self.right_child, self.right_merk_hash)?,
+        ))
+    }
+
+    /// Recompute hashes of the node and compare them with what was received
+    /// by the node itself and its loaded children.
+    pub(crate) fn verify_hashes(&self, subtree: &Subtree, key: KeySlice) -> Vec<HashMismatch> {
+        let mut mismatches = Vec::new();
+
+        if let (Some(value_hash), Some(received)) = (&self.value_hash, self.kv_digest_hash) {
+            let computed = kv_digest_to_kv_hash(key, value_hash);
+            if computed != received {
+                mismatches.push(HashMismatch::KvHash { received, computed });
+            }
+        }
+
+        let child_hash = |child_key: &Option<Vec<u8>>| {
+            child_key
+                .as_ref()
+                .and_then(|k| subtree.nodes.get(k))
+                .and_then(Node::node_hash)
+        };
+
+        if let (Some(received), Some(computed)) =
+            (self.left_merk_hash, child_hash(&self.left_child))
+        {
+            if computed != received {
+                mismatches.push(HashMismatch::LeftChild { received, computed });
+            }
+        }
+
+        if let (Some(received), Some(computed)) =
+            (self.right_merk_hash, child_hash(&self.right_child))
+        {
+            if computed != received {
+                mismatches.push(HashMismatch::RightChild { received, computed });
+            }
+        }
+
+        mismatches
+    }
+}
+
+impl Subtree {
+    /// Root hash of a subtree if its root node was fetched, this is the hash
+    /// that gets combined into the value hash of the parent subtree element.
+    pub(crate) fn root_hash(&self) -> Option<CryptoHash> {
+        self.root_node().and_then(Node::node_hash)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hashed_item(key: &[u8], value: &[u8]) -> Node {
+        let value_hash = value_hash(value);
+        let mut node = Node::new_item(value.to_vec());
+        node.value_hash = Some(value_hash);
+        node.kv_digest_hash = Some(kv_digest_to_kv_hash(key, &value_hash));
+        node
+    }
+
+    #[test]
+    fn varint_encoding() {
+        let mut buf = Vec::new();
+        encode_varint(300, &mut buf);
+        assert_eq!(buf, vec![0xac, 0x02]);
+    }
+
+    #[test]
+    fn verify_consistent_nodes() {
+        let mut subtree = Subtree::new_root(b"root".to_vec());
+        let left = hashed_item(b"a", b"left");
+        let mut root = hashed_item(b"root", b"value").with_left_child(b"a".to_vec());
+        root.left_merk_hash = left.node_hash();
+        subtree.insert(b"a".to_vec(), left);
+        subtree.insert(b"root".to_vec(), root);
+
+        let root = subtree.root_node().unwrap();
+        assert!(root.verify_hashes(&subtree, b"root").is_empty());
+        assert_eq!(
+            subtree.root_hash(),
+            Some(node_hash(
+                &root.kv_digest_hash.unwrap(),
+                &root.left_merk_hash.unwrap(),
+                &NULL_HASH
+            ))
+        );
+    }
+
+    #[test]
+    fn verify_detects_mismatches() {
+        let mut subtree = Subtree::new_root(b"root".to_vec());
+        let left = hashed_item(b"a", b"left");
+        let mut root = hashed_item(b"root", b"value").with_left_child(b"a".to_vec());
+        root.left_merk_hash = Some([1; 32]);
+        root.value_hash = Some(value_hash(b"tampered"));
+        subtree.insert(b"a".to_vec(), left);
+        subtree.insert(b"root".to_vec(), root);
+
+        let mismatches = subtree
+            .root_node()
+            .unwrap()
+            .verify_hashes(&subtree, b"root");
+        assert_eq!(mismatches.len(), 2);
+        assert!(matches!(mismatches[0], HashMismatch::KvHash { .. }));
+        assert!(
+            matches!(mismatches[1], HashMismatch::LeftChild { received, .. } if received == [1; 32])
+        );
+    }
+}
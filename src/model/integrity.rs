@@ -0,0 +1,318 @@
+//! Structural integrity checks of loaded Merk trees.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
+
+use super::{Key, Path, Subtree, Tree};
+use crate::ui::bytes_as_hex;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Violation {
+    /// A descendant is on the wrong side of its ancestor's key
+    BstOrder { ancestor: Key, left: bool },
+    /// The node is a child of more than one node
+    SharedChild { parents: Vec<Key> },
+    /// The node is reachable from itself
+    Cycle,
+    /// The subtree root node is a child of another node
+    RootHasParent { parent: Key },
+    /// A cluster root is a child of a loaded node or is not loaded itself
+    BadClusterRoot { parent: Option<Key> },
+    /// A loaded node without a parent is neither a root nor a cluster root
+    Orphan,
+    /// A node is waited for but it's either loaded or not a child of anyone
+    BadWaitlistEntry { loaded: bool },
+    /// A child is not loaded and nobody waits for it
+    NotWaited { parent: Key },
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::BstOrder { ancestor, left } => write!(
+                f,
+                "Key must be {} than the key of ancestor {}",
+                if *left { "less" } else { "greater" },
+                bytes_as_hex(ancestor)
+            ),
+            Violation::SharedChild { parents } => write!(
+                f,
+                "Node has {} parents: {}",
+                parents.len(),
+                parents
+                    .iter()
+                    .map(|p| bytes_as_hex(p))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Violation::Cycle => write!(f, "Node is a part of a cycle"),
+            Violation::RootHasParent { parent } => {
+                write!(f, "Root node is a child of {}", bytes_as_hex(parent))
+            }
+            Violation::BadClusterRoot {
+                parent: Some(parent),
+            } => write!(f, "Cluster root is a child of {}", bytes_as_hex(parent)),
+            Violation::BadClusterRoot { parent: None } => write!(f, "Cluster root is not loaded"),
+            Violation::Orphan => write!(f, "Node has no parent and is not a cluster root"),
+            Violation::BadWaitlistEntry { loaded: true } => {
+                write!(f, "Node is waited for but already loaded")
+            }
+            Violation::BadWaitlistEntry { loaded: false } => {
+                write!(f, "Node is waited for but no one refers to it")
+            }
+            Violation::NotWaited { parent } => write!(
+                f,
+                "Child of {} is not loaded and not waited for",
+                bytes_as_hex(parent)
+            ),
+        }
+    }
+}
+
+/// A violation found at the node with the key
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Issue {
+    pub(crate) path: Path,
+    pub(crate) key: Key,
+    pub(crate) violation: Violation,
+}
+
+impl Tree {
+    /// Check all loaded subtrees.
+    pub(crate) fn check_integrity(&self) -> Vec<Issue> {
+        self.subtrees
+            .iter()
+            .flat_map(|(path, subtree)| {
+                subtree
+                    .check_integrity()
+                    .into_iter()
+                    .map(|(key, violation)| Issue {
+                        path: path.clone(),
+                        key,
+                        violation,
+                    })
+            })
+            .collect()
+    }
+}
+
+enum Step<'a> {
+    Enter {
+        key: &'a Key,
+        lower: Option<&'a Key>,
+        upper: Option<&'a Key>,
+    },
+    Exit(&'a Key),
+}
+
+impl Subtree {
+    fn check_integrity(&self) -> Vec<(Key, Violation)> {
+        let mut violations = Vec::new();
+
+        let mut parents: BTreeMap<&Key, Vec<&Key>> = BTreeMap::new();
+        for (key, node) in self.nodes.iter() {
+            for child in node.left_child.iter().chain(node.right_child.iter()) {
+                parents.entry(child).or_default().push(key);
+            }
+        }
+
+        for (child, child_parents) in parents.iter() {
+            if child_parents.len() > 1 {
+                violations.push((
+                    (*child).clone(),
+                    Violation::SharedChild {
+                        parents: child_parents.iter().map(|p| (*p).clone()).collect(),
+                    },
+                ));
+            }
+            if !self.nodes.contains_key(*child) && !self.waitlist.contains(*child) {
+                violations.push((
+                    (*child).clone(),
+                    Violation::NotWaited {
+                        parent: child_parents[0].clone(),
+                    },
+                ));
+            }
+        }
+
+        if let Some(parent) = self
+            .root_node
+            .as_ref()
+            .and_then(|root| parents.get(root))
+            .and_then(|p| p.first())
+        {
+            violations.push((
+                self.root_node.clone().expect("checked above"),
+                Violation::RootHasParent {
+                    parent: (*parent).clone(),
+                },
+            ));
+        }
+
+        for cluster_root in self.cluster_roots.iter() {
+            let parent = parents.get(cluster_root).and_then(|p| p.first());
+            if parent.is_some() || !self.nodes.contains_key(cluster_root) {
+                violations.push((
+                    cluster_root.clone(),
+                    Violation::BadClusterRoot {
+                        parent: parent.map(|p| (*p).clone()),
+                    },
+                ));
+            }
+        }
+
+        for waited in self.waitlist.iter() {
+            let loaded = self.nodes.contains_key(waited);
+            if loaded || !parents.contains_key(waited) {
+                violations.push((waited.clone(), Violation::BadWaitlistEntry { loaded }));
+            }
+        }
+
+        // Walk from every node that has no parent checking keys order and looking
+        // for cycles, nodes left unvisited are reachable only through cycles
+        let mut visited = BTreeSet::new();
+        let mut on_stack = BTreeSet::new();
+        for top in self.nodes.keys().filter(|key| !parents.contains_key(key)) {
+            if self.root_node.as_ref() != Some(top) && !self.cluster_roots.contains(top) {
+                violations.push((top.clone(), Violation::Orphan));
+            }
+
+            let mut stack = vec![Step::Enter {
+                key: top,
+                lower: None,
+                upper: None,
+            }];
+            while let Some(step) = stack.pop() {
+                let (key, lower, upper) = match step {
+                    Step::Exit(key) => {
+                        on_stack.remove(key);
+                        continue;
+                    }
+                    Step::Enter { key, lower, upper } => (key, lower, upper),
+                };
+                if on_stack.contains(key) {
+                    violations.push((key.clone(), Violation::Cycle));
+                    continue;
+                }
+                let Some(node) = self.nodes.get(key) else {
+                    continue;
+                };
+                if let Some(lower) = lower.filter(|lower| key <= *lower) {
+                    violations.push((
+                        key.clone(),
+                        Violation::BstOrder {
+                            ancestor: lower.clone(),
+                            left: false,
+                        },
+                    ));
+                }
+                if let Some(upper) = upper.filter(|upper| key >= *upper) {
+                    violations.push((
+                        key.clone(),
+                        Violation::BstOrder {
+                            ancestor: upper.clone(),
+                            left: true,
+                        },
+                    ));
+                }
+
+                if !visited.insert(key) {
+                    // Shared children are reported already
+                    continue;
+                }
+
+                on_stack.insert(key);
+                stack.push(Step::Exit(key));
+                if let Some(left) = &node.left_child {
+                    stack.push(Step::Enter {
+                        key: left,
+                        lower,
+                        upper: Some(key),
+                    });
+                }
+                if let Some(right) = &node.right_child {
+                    stack.push(Step::Enter {
+                        key: right,
+                        lower: Some(key),
+                        upper,
+                    });
+                }
+            }
+        }
+
+        for key in self.nodes.keys().filter(|key| !visited.contains(key)) {
+            violations.push((key.clone(), Violation::Cycle));
+        }
+
+        violations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Node;
+
+    #[test]
+    fn consistent_subtree() {
+        let mut subtree = Subtree::new_root(b"b".to_vec());
+        subtree.insert(
+            b"b".to_vec(),
+            Node::new_item(vec![])
+                .with_left_child(b"a".to_vec())
+                .with_right_child(b"c".to_vec()),
+        );
+        subtree.insert(b"a".to_vec(), Node::new_item(vec![]));
+        assert!(subtree.check_integrity().is_empty());
+    }
+
+    #[test]
+    fn bst_order_and_shared_children() {
+        let mut subtree = Subtree::new_root(b"b".to_vec());
+        subtree.insert(
+            b"b".to_vec(),
+            Node::new_item(vec![])
+                .with_left_child(b"c".to_vec())
+                .with_right_child(b"d".to_vec()),
+        );
+        subtree.insert(b"c".to_vec(), Node::new_item(vec![]));
+        subtree.insert(
+            b"d".to_vec(),
+            Node::new_item(vec![]).with_left_child(b"c".to_vec()),
+        );
+
+        let violations = subtree.check_integrity();
+        assert!(violations.contains(&(
+            b"c".to_vec(),
+            Violation::SharedChild {
+                parents: vec![b"b".to_vec(), b"d".to_vec()]
+            }
+        )));
+        assert!(violations.contains(&(
+            b"c".to_vec(),
+            Violation::BstOrder {
+                ancestor: b"b".to_vec(),
+                left: true
+            }
+        )));
+    }
+
+    #[test]
+    fn cycles() {
+        let mut subtree = Subtree::new();
+        subtree.insert(
+            b"a".to_vec(),
+            Node::new_item(vec![]).with_right_child(b"b".to_vec()),
+        );
+        subtree.insert(
+            b"b".to_vec(),
+            Node::new_item(vec![]).with_left_child(b"a".to_vec()),
+        );
+
+        let violations = subtree.check_integrity();
+        assert!(violations.contains(&(b"a".to_vec(), Violation::Cycle)));
+        assert!(violations.contains(&(b"b".to_vec(), Violation::Cycle)));
+    }
+}
@@ -0,0 +1,238 @@
+//! Filtering and sorting of keys listed in collapsed subtrees.
+
+use std::{cell::RefMut, cmp::Ordering, mem};
+
+use super::{Element, KeySlice, Node, NodeCtx, Subtree, SubtreeCtx};
+use crate::ui::{bytes_from_display_variant, DisplayVariant};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, strum::EnumIter, strum::AsRefStr)]
+pub(crate) enum KeyMatch {
+    #[default]
+    Prefix,
+    Substring,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, strum::EnumIter, strum::AsRefStr)]
+pub(crate) enum ValuePredicate {
+    #[default]
+    Any,
+    /// Item value contains the bytes
+    Contains,
+    #[strum(serialize = "Length ≥")]
+    LengthAtLeast,
+    #[strum(serialize = "Length ≤")]
+    LengthAtMost,
+    #[strum(serialize = "Sum ≥")]
+    SumAtLeast,
+    #[strum(serialize = "Sum ≤")]
+    SumAtMost,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, strum::EnumIter, strum::AsRefStr)]
+pub(crate) enum SortBy {
+    #[default]
+    Key,
+    #[strum(serialize = "Value length")]
+    ValueLength,
+    Sum,
+}
+
+/// Per subtree settings of which loaded keys to list and in what order
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct ListingFilter {
+    /// Display variant to parse key and value inputs
+    pub(crate) display_variant: DisplayVariant,
+    pub(crate) key: String,
+    pub(crate) key_match: KeyMatch,
+    /// Variant of elements to keep, values of the variant fields are ignored
+    pub(crate) element_type: Option<Element>,
+    pub(crate) value_predicate: ValuePredicate,
+    pub(crate) value: String,
+    pub(crate) sort_by: SortBy,
+    pub(crate) descending: bool,
+    /// Page of the filtered keys
+    pub(crate) page: usize,
+}
+
+fn value_len(node: &Node) -> Option<usize> {
+    match &node.element {
+        Element::Item { value } => Some(value.len()),
+        _ => None,
+    }
+}
+
+fn sum(node: &Node) -> Option<i64> {
+    match node.element {
+        Element::SumItem { value } => Some(value),
+        Element::Sumtree { sum, .. } => Some(sum),
+        _ => None,
+    }
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    needle.is_empty()
+        || haystack
+            .windows(needle.len())
+            .any(|window| window == needle)
+}
+
+impl ListingFilter {
+    /// Anything differs from listing all keys in key order
+    pub(crate) fn is_active(&self) -> bool {
+        !self.key.is_empty()
+            || self.element_type.is_some()
+            || self.value_predicate != ValuePredicate::Any
+            || self.sort_by != SortBy::Key
+            || self.descending
+    }
+
+    /// Key or value input cannot be parsed, nothing is listed in that case
+    pub(crate) fn is_malformed(&self) -> bool {
+        let parses_as_bytes =
+            |text: &str| bytes_from_display_variant(text, self.display_variant).is_some();
+        let value_is_valid = match self.value_predicate {
+            ValuePredicate::Any => true,
+            ValuePredicate::Contains => parses_as_bytes(&self.value),
+            ValuePredicate::LengthAtLeast | ValuePredicate::LengthAtMost => {
+                self.value.trim().parse::<usize>().is_ok()
+            }
+            ValuePredicate::SumAtLeast | ValuePredicate::SumAtMost => {
+                self.value.trim().parse::<i64>().is_ok()
+            }
+        };
+        !parses_as_bytes(&self.key) || !value_is_valid
+    }
+
+    pub(crate) fn matches(&self, key: KeySlice, node: &Node) -> bool {
+        let Some(key_pattern) = bytes_from_display_variant(&self.key, self.display_variant) else {
+            return false;
+        };
+        let key_matches = match self.key_match {
+            KeyMatch::Prefix => key.starts_with(&key_pattern),
+            KeyMatch::Substring => contains(key, &key_pattern),
+        };
+        let type_matches = self
+            .element_type
+            .as_ref()
+            .is_none_or(|element| mem::discriminant(element) == mem::discriminant(&node.element));
+        let value = self.value.trim();
+        let value_matches = match self.value_predicate {
+            ValuePredicate::Any => true,
+            ValuePredicate::Contains => {
+                match (
+                    &node.element,
+                    bytes_from_display_variant(value, self.display_variant),
+                ) {
+                    (Element::Item { value }, Some(pattern)) => contains(value, &pattern),
+                    _ => false,
+                }
+            }
+            ValuePredicate::LengthAtLeast => value_len(node)
+                .zip(value.parse().ok())
+                .is_some_and(|(len, bound)| len >= bound),
+            ValuePredicate::LengthAtMost => value_len(node)
+                .zip(value.parse().ok())
+                .is_some_and(|(len, bound)| len <= bound),
+            ValuePredicate::SumAtLeast => sum(node)
+                .zip(value.parse().ok())
+                .is_some_and(|(sum, bound)| sum >= bound),
+            ValuePredicate::SumAtMost => sum(node)
+                .zip(value.parse().ok())
+                .is_some_and(|(sum, bound)| sum <= bound),
+        };
+        key_matches && type_matches && value_matches
+    }
+
+    /// Order of nodes, the ones without a value to sort by go last.
+    fn compare(&self, a: (KeySlice, &Node), b: (KeySlice, &Node)) -> Ordering {
+        let directed = |ordering: Ordering| {
+            if self.descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        };
+        let some_first = |a: Option<i128>, b: Option<i128>| match (a, b) {
+            (Some(a), Some(b)) => directed(a.cmp(&b)),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        };
+        match self.sort_by {
+            SortBy::Key => directed(a.0.cmp(b.0)),
+            SortBy::ValueLength => some_first(
+                value_len(a.1).map(|len| len as i128),
+                value_len(b.1).map(|len| len as i128),
+            ),
+            SortBy::Sum => some_first(sum(a.1).map(i128::from), sum(b.1).map(i128::from)),
+        }
+    }
+}
+
+impl Subtree {
+    pub(crate) fn listing_filter_mut(&self) -> RefMut<ListingFilter> {
+        RefMut::map(self.ui_state.borrow_mut(), |state| {
+            &mut state.listing_filter
+        })
+    }
+}
+
+impl<'a> SubtreeCtx<'a> {
+    /// Loaded nodes passing the subtree's listing filter in its order.
+    pub(crate) fn filtered_nodes(&self) -> Vec<NodeCtx<'a>> {
+        let filter = self.subtree.ui_state.borrow().listing_filter.clone();
+        let mut nodes: Vec<_> = self
+            .subtree
+            .nodes
+            .iter()
+            .filter(|(key, node)| filter.matches(key, node))
+            .map(|(key, node)| NodeCtx {
+                node,
+                path: self.path,
+                key,
+                subtree_ctx: *self,
+            })
+            .collect();
+        // Stable sort keeps key order for equal values
+        nodes.sort_by(|a, b| filter.compare((a.key, a.node), (b.key, b.node)));
+        nodes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filter_and_sort() {
+        let mut filter = ListingFilter {
+            display_variant: DisplayVariant::String,
+            key: "ab".to_owned(),
+            ..Default::default()
+        };
+        let item = Node::new_item(b"hello".to_vec());
+        assert!(filter.matches(b"abc", &item));
+        assert!(!filter.matches(b"cab", &item));
+        filter.key_match = KeyMatch::Substring;
+        assert!(filter.matches(b"cab", &item));
+
+        filter.element_type = Some(Element::SumItem { value: 0 });
+        assert!(!filter.matches(b"cab", &item));
+        assert!(filter.matches(b"cab", &Node::new_sum_item(5)));
+
+        filter.value_predicate = ValuePredicate::SumAtLeast;
+        filter.value = "6".to_owned();
+        assert!(!filter.matches(b"cab", &Node::new_sum_item(5)));
+        filter.value = "six".to_owned();
+        assert!(filter.is_malformed());
+
+        filter.sort_by = SortBy::Sum;
+        filter.descending = true;
+        let small = Node::new_sum_item(1);
+        let big = Node::new_sum_item(2);
+        assert_eq!(
+            filter.compare((b"a", &small), (b"b", &big)),
+            Ordering::Greater
+        );
+    }
+}
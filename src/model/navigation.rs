@@ -0,0 +1,152 @@
+//! Moving the selection through the tree with keyboard.
+
+use super::{Element, Key, KeySlice, Path, Tree};
+
+/// Where to move the selected node
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Step {
+    /// Parent node within the subtree or the parent subtree's node at the root
+    Parent,
+    Left,
+    Right,
+    /// Node of the parent subtree that represents the current subtree
+    ParentSubtree,
+    /// Root node of the subtree represented by the selected node
+    ChildSubtree,
+}
+
+impl Tree {
+    /// Key of a loaded node that has the node as a child.
+    pub(crate) fn parent_node(&self, path: &Path, key: KeySlice) -> Option<Key> {
+        self.subtrees
+            .get(path)?
+            .nodes
+            .iter()
+            .find_map(|(parent, node)| {
+                (node.left_child.as_deref() == Some(key)
+                    || node.right_child.as_deref() == Some(key))
+                .then(|| parent.clone())
+            })
+    }
+
+    /// Make the node visible along with its parent subtrees and the branches
+    /// leading to it from its subtree's root.
+    pub(crate) fn reveal_node(&self, path: &Path, key: KeySlice) {
+        self.reveal_subtree(path);
+        let Some(subtree_ctx) = self.get_subtree(path) else {
+            return;
+        };
+        let mut key = key.to_vec();
+        // Bounded in case of malformed data with cycles
+        for _ in 0..subtree_ctx.subtree().nodes.len() {
+            let Some(parent) = self.parent_node(path, &key) else {
+                break;
+            };
+            if let Some(parent_ctx) = subtree_ctx.get_node(&parent) {
+                if parent_ctx.node().left_child.as_deref() == Some(key.as_slice()) {
+                    parent_ctx.set_left_visible();
+                } else {
+                    parent_ctx.set_right_visible();
+                }
+            }
+            key = parent;
+        }
+    }
+
+    /// Move the selection and make the new selected node visible. Returns the
+    /// new selection if it has moved, the node might not be loaded yet.
+    /// With no selection the root subtree's root node is selected.
+    pub(crate) fn navigate(&self, step: Step) -> Option<(Path, Key)> {
+        let Some((path, key)) = self.selected_node() else {
+            let root = self.subtrees.get(&Path::default())?.root_node.clone()?;
+            self.select_node(Path::default(), root.clone());
+            return Some((Path::default(), root));
+        };
+
+        let (path, key) = match step {
+            Step::Parent => match self.parent_node(&path, &key) {
+                Some(parent) => (path, parent),
+                None => return self.navigate(Step::ParentSubtree),
+            },
+            Step::Left | Step::Right => {
+                let node_ctx = self.get_subtree(&path)?.get_node(&key)?;
+                let child = if step == Step::Left {
+                    node_ctx.set_left_visible();
+                    node_ctx.node().left_child.clone()?
+                } else {
+                    node_ctx.set_right_visible();
+                    node_ctx.node().right_child.clone()?
+                };
+                (path, child)
+            }
+            Step::ParentSubtree => {
+                let mut parent_path = path;
+                let key = parent_path.pop()?;
+                (parent_path, key)
+            }
+            Step::ChildSubtree => {
+                let (Element::Subtree { root_key } | Element::Sumtree { root_key, .. }) =
+                    &self.get_node(&path, &key)?.element
+                else {
+                    return None;
+                };
+                let root_key = root_key.clone()?;
+                let mut child_path = path;
+                child_path.push(key);
+                (child_path, root_key)
+            }
+        };
+
+        self.reveal_subtree(&path);
+        self.select_node(path.clone(), key.clone());
+        Some((path, key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Node;
+
+    #[test]
+    fn navigation_steps() {
+        let mut tree = Tree::new();
+        tree.set_root(b"b".to_vec());
+        tree.insert(
+            Path::default(),
+            b"b".to_vec(),
+            Node::new_item(vec![]).with_left_child(b"a".to_vec()),
+        );
+        tree.insert(
+            Path::default(),
+            b"a".to_vec(),
+            Node::new_subtree(Some(b"x".to_vec())),
+        );
+        let child_path: Path = vec![b"a".to_vec()].into();
+
+        assert_eq!(
+            tree.navigate(Step::Left),
+            Some((Path::default(), b"b".to_vec()))
+        );
+        assert_eq!(tree.navigate(Step::Right), None);
+        assert_eq!(
+            tree.navigate(Step::Left),
+            Some((Path::default(), b"a".to_vec()))
+        );
+        assert_eq!(
+            tree.navigate(Step::ChildSubtree),
+            Some((child_path.clone(), b"x".to_vec()))
+        );
+        assert!(tree.subtrees[&child_path].visible());
+        assert_eq!(
+            tree.navigate(Step::Parent),
+            Some((Path::default(), b"a".to_vec()))
+        );
+        assert_eq!(
+            tree.navigate(Step::Parent),
+            Some((Path::default(), b"b".to_vec()))
+        );
+        assert_eq!(tree.navigate(Step::ParentSubtree), None);
+        assert_eq!(tree.selected_node(), Some((Path::default(), b"b".to_vec())));
+    }
+}
@@ -0,0 +1,205 @@
+//! Listing keys of collapsed subtrees page by page in key order as GroveDB
+//! has them, not only those that happen to be fetched.
+
+use std::cell::RefMut;
+
+use grovedbg_types::{PathQuery, Query, QueryItem, SizedQuery, SubqueryBranch};
+
+use super::{Key, NodeCtx, Path, Subtree, SubtreeCtx, Tree};
+use crate::ui::DisplayVariant;
+
+pub(crate) const DEFAULT_PAGE_SIZE: usize = 10;
+pub(crate) const MAX_PAGE_SIZE: usize = 100;
+
+/// Page size to both query and cut pages with, a page can't be empty or
+/// larger than a query is allowed to return.
+pub(crate) fn clamp_page_size(page_size: usize) -> usize {
+    page_size.clamp(1, MAX_PAGE_SIZE)
+}
+
+/// Keys of a subtree fetched with one range query
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct KeysPage {
+    pub(crate) keys: Vec<Key>,
+    /// There are keys before the first one of the page
+    pub(crate) has_prev: bool,
+    /// There are keys after the last one of the page
+    pub(crate) has_next: bool,
+}
+
+impl KeysPage {
+    /// The page lists all keys of the subtree
+    pub(crate) fn is_complete(&self) -> bool {
+        !self.has_prev && !self.has_next
+    }
+}
+
+/// Where a page starts from
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum PageCursor {
+    First,
+    /// Keys after the last key of the current page
+    After(Key),
+    /// Keys before the first key of the current page
+    Before(Key),
+    /// Keys starting with the one user asked to jump to
+    From(Key),
+}
+
+impl PageCursor {
+    /// Path query for a page, one extra key is requested to tell if there
+    /// are more keys in that direction. The page size is expected to be
+    /// clamped with [`clamp_page_size`].
+    pub(crate) fn path_query(&self, path: &Path, page_size: usize) -> PathQuery {
+        let (item, left_to_right) = match self {
+            PageCursor::First => (QueryItem::RangeFull, true),
+            PageCursor::After(key) => (QueryItem::RangeAfter(key.clone()), true),
+            PageCursor::Before(key) => (QueryItem::RangeTo(key.clone()), false),
+            PageCursor::From(key) => (QueryItem::RangeFrom(key.clone()), true),
+        };
+        PathQuery {
+            path: path.0.clone(),
+            query: SizedQuery {
+                query: Query {
+                    items: vec![item],
+                    default_subquery_branch: SubqueryBranch {
+                        subquery_path: None,
+                        subquery: None,
+                    },
+                    conditional_subquery_branches: Vec::new(),
+                    left_to_right,
+                },
+                limit: Some((page_size + 1) as u16),
+                offset: None,
+            },
+        }
+    }
+
+    /// Build a page out of keys returned for the query in the query order.
+    pub(crate) fn page(&self, mut keys: Vec<Key>, page_size: usize) -> KeysPage {
+        let more = keys.len() > page_size;
+        keys.truncate(page_size);
+        match self {
+            PageCursor::First => KeysPage {
+                keys,
+                has_prev: false,
+                has_next: more,
+            },
+            PageCursor::After(_) => KeysPage {
+                keys,
+                has_prev: true,
+                has_next: more,
+            },
+            PageCursor::Before(_) => {
+                keys.reverse();
+                KeysPage {
+                    keys,
+                    has_prev: more,
+                    has_next: true,
+                }
+            }
+            // Nothing tells whether the key is the first one
+            PageCursor::From(_) => KeysPage {
+                keys,
+                has_prev: true,
+                has_next: more,
+            },
+        }
+    }
+}
+
+impl Subtree {
+    pub(crate) fn keys_page(&self) -> Option<&KeysPage> {
+        self.keys_page.as_ref()
+    }
+
+    /// Key typed by user to start a page from
+    pub(crate) fn jump_key_mut(&self) -> RefMut<String> {
+        RefMut::map(self.ui_state.borrow_mut(), |state| &mut state.jump_key)
+    }
+
+    /// Representation to parse the jump key with
+    pub(crate) fn jump_key_display_variant_mut(&self) -> RefMut<DisplayVariant> {
+        RefMut::map(self.ui_state.borrow_mut(), |state| {
+            &mut state.jump_key_display_variant
+        })
+    }
+}
+
+impl Tree {
+    pub(crate) fn set_keys_page(&mut self, path: &Path, page: KeysPage) {
+        if let Some(subtree) = self.subtrees.get_mut(path) {
+            subtree.keys_page = Some(page);
+        }
+    }
+}
+
+impl<'a> SubtreeCtx<'a> {
+    /// Nodes to list in the collapsed view: a page of filtered loaded nodes if
+    /// the listing filter is active, otherwise the fetched page if any or the
+    /// first loaded nodes.
+    pub(crate) fn iter_listed_nodes(
+        &self,
+        page_size: usize,
+    ) -> Box<dyn Iterator<Item = NodeCtx<'a>> + 'a> {
+        let subtree_ctx = *self;
+        let filter_page = {
+            let filter = self.subtree.listing_filter_mut();
+            filter.is_active().then_some(filter.page)
+        };
+        match (filter_page, &self.subtree.keys_page) {
+            (Some(filter_page), _) => Box::new(
+                self.filtered_nodes()
+                    .into_iter()
+                    .skip(filter_page * page_size)
+                    .take(page_size),
+            ),
+            (None, Some(page)) => Box::new(
+                page.keys
+                    .iter()
+                    .filter_map(move |key| subtree_ctx.get_node(key)),
+            ),
+            (None, None) => Box::new(self.subtree.nodes.iter().take(page_size).map(
+                move |(key, node)| NodeCtx {
+                    node,
+                    path: subtree_ctx.path,
+                    key,
+                    subtree_ctx,
+                },
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pages_from_query_results() {
+        let keys = |range: std::ops::Range<u8>| range.map(|k| vec![k]).collect::<Vec<_>>();
+
+        let page = PageCursor::First.page(keys(0..3), 2);
+        assert_eq!(page.keys, keys(0..2));
+        assert!(!page.has_prev && page.has_next);
+
+        let page = PageCursor::After(vec![1]).page(keys(2..4), 2);
+        assert!(page.has_prev && !page.has_next);
+
+        // Results of backward queries come in descending order
+        let page = PageCursor::Before(vec![4]).page(vec![vec![3], vec![2], vec![1]], 2);
+        assert_eq!(page.keys, keys(2..4));
+        assert!(page.has_prev && page.has_next);
+
+        let page = PageCursor::First.page(keys(0..2), 2);
+        assert!(page.is_complete());
+
+        // Oversized pages are queried and cut at the same size
+        let page_size = clamp_page_size(MAX_PAGE_SIZE * 2);
+        let query = PageCursor::First.path_query(&Path::default(), page_size);
+        assert_eq!(query.query.limit, Some(MAX_PAGE_SIZE as u16 + 1));
+        let page = PageCursor::First.page(keys(0..MAX_PAGE_SIZE as u8 + 1), page_size);
+        assert_eq!(page.keys.len(), MAX_PAGE_SIZE);
+        assert!(page.has_next);
+    }
+}
@@ -0,0 +1,683 @@
+//! GroveDB proofs decoding, verification and conversion into a [Tree] to draw.
+//!
+//! A GroveDB proof is a bincode encoded layer of Merk proof operations with a
+//! map of lower layers, one for each subtree the query went into. Executing
+//! Merk proof operations builds a partial Merk tree with pruned branches
+//! replaced by their hashes.
+
+use std::collections::BTreeMap;
+
+use super::{
+    hash::{combine_hash, kv_digest_to_kv_hash, node_hash, value_hash, CryptoHash, NULL_HASH},
+    Element, FeatureType, Key, Node, Path, Tree,
+};
+use crate::fetch::ElementCtx;
+
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub(crate) enum ProofError {
+    #[error("unexpected end of proof")]
+    UnexpectedEnd,
+    #[error("bad varint")]
+    BadVarint,
+    #[error("unknown {what} variant {variant}")]
+    UnknownVariant { what: &'static str, variant: u64 },
+    #[error("unknown Merk proof operation {0:#04x}")]
+    UnknownOp(u8),
+    #[error("Merk proof operations don't form a single tree")]
+    BadStack,
+    #[error("{0} trailing bytes left")]
+    TrailingBytes(usize),
+    #[error("element has a reference without key")]
+    ReferenceWithoutKey,
+}
+
+/// Merk proof node, pushed by proof operations
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum ProofNode {
+    Hash(CryptoHash),
+    KVHash(CryptoHash),
+    KV(Key, Vec<u8>),
+    KVValueHash(Key, Vec<u8>, CryptoHash),
+    KVDigest(Key, CryptoHash),
+    KVRefValueHash(Key, Vec<u8>, CryptoHash),
+    KVValueHashFeatureType(Key, Vec<u8>, CryptoHash, FeatureType),
+}
+
+impl ProofNode {
+    fn key(&self) -> Option<&Key> {
+        match self {
+            ProofNode::Hash(_) | ProofNode::KVHash(_) => None,
+            ProofNode::KV(key, ..)
+            | ProofNode::KVValueHash(key, ..)
+            | ProofNode::KVDigest(key, ..)
+            | ProofNode::KVRefValueHash(key, ..)
+            | ProofNode::KVValueHashFeatureType(key, ..) => Some(key),
+        }
+    }
+
+    fn value(&self) -> Option<&[u8]> {
+        match self {
+            ProofNode::KV(_, value)
+            | ProofNode::KVValueHash(_, value, _)
+            | ProofNode::KVRefValueHash(_, value, _)
+            | ProofNode::KVValueHashFeatureType(_, value, ..) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Value hash the node commits to, if it's known
+    fn value_hash(&self) -> Option<CryptoHash> {
+        match self {
+            ProofNode::Hash(_) | ProofNode::KVHash(_) => None,
+            ProofNode::KV(_, value) => Some(value_hash(value)),
+            ProofNode::KVValueHash(_, _, value_hash)
+            | ProofNode::KVDigest(_, value_hash)
+            | ProofNode::KVValueHashFeatureType(_, _, value_hash, _) => Some(*value_hash),
+            ProofNode::KVRefValueHash(_, referenced_value, node_value_hash) => {
+                Some(combine_hash(node_value_hash, &value_hash(referenced_value)))
+            }
+        }
+    }
+
+    fn kv_hash(&self) -> Option<CryptoHash> {
+        match self {
+            ProofNode::Hash(_) => None,
+            ProofNode::KVHash(kv_hash) => Some(*kv_hash),
+            _ => Some(kv_digest_to_kv_hash(self.key()?, &self.value_hash()?)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Op {
+    Push(ProofNode),
+    PushInverted(ProofNode),
+    Parent,
+    Child,
+    ParentInverted,
+    ChildInverted,
+}
+
+/// Partial Merk tree built by execution of proof operations
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ProofTree {
+    pub(crate) node: ProofNode,
+    pub(crate) left: Option<Box<ProofTree>>,
+    pub(crate) right: Option<Box<ProofTree>>,
+}
+
+impl ProofTree {
+    pub(crate) fn hash(&self) -> CryptoHash {
+        let child_hash =
+            |child: &Option<Box<ProofTree>>| child.as_ref().map(|c| c.hash()).unwrap_or(NULL_HASH);
+        match (&self.node, self.node.kv_hash()) {
+            (ProofNode::Hash(hash), _) => *hash,
+            (_, Some(kv_hash)) => {
+                node_hash(&kv_hash, &child_hash(&self.left), &child_hash(&self.right))
+            }
+            (_, None) => unreachable!("only hash nodes have no kv hash"),
+        }
+    }
+
+    fn find(&self, key: &[u8]) -> Option<&ProofNode> {
+        if self.node.key().map(|k| k.as_slice()) == Some(key) {
+            return Some(&self.node);
+        }
+        self.left
+            .as_ref()
+            .and_then(|l| l.find(key))
+            .or_else(|| self.right.as_ref().and_then(|r| r.find(key)))
+    }
+
+    fn attach(&mut self, left: bool, child: ProofTree) -> Result<(), ProofError> {
+        let slot = if left {
+            &mut self.left
+        } else {
+            &mut self.right
+        };
+        if slot.is_some() {
+            return Err(ProofError::BadStack);
+        }
+        *slot = Some(Box::new(child));
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct LayerProof {
+    pub(crate) tree: ProofTree,
+    pub(crate) lower_layers: BTreeMap<Key, LayerProof>,
+}
+
+/// Mismatch between a subtree element in a proof layer and the root hash of
+/// the lower layer proof for this subtree.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct LayerMismatch {
+    pub(crate) path: Path,
+    pub(crate) key: Key,
+    pub(crate) reason: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct GroveDbProof {
+    pub(crate) root_layer: LayerProof,
+}
+
+impl GroveDbProof {
+    pub(crate) fn decode(bytes: &[u8]) -> Result<Self, ProofError> {
+        let mut reader = Reader { bytes };
+        match reader.varint()? {
+            0 => {}
+            variant => {
+                return Err(ProofError::UnknownVariant {
+                    what: "GroveDB proof",
+                    variant,
+                })
+            }
+        }
+        let root_layer = reader.layer()?;
+        // Prove options, absent in older proofs
+        if !reader.bytes.is_empty() {
+            reader.u8()?;
+        }
+        if !reader.bytes.is_empty() {
+            return Err(ProofError::TrailingBytes(reader.bytes.len()));
+        }
+        Ok(GroveDbProof { root_layer })
+    }
+
+    /// GroveDB root hash this proof leads to
+    pub(crate) fn root_hash(&self) -> CryptoHash {
+        self.root_layer.tree.hash()
+    }
+
+    /// Check that every lower layer is bound to a subtree element of its
+    /// parent layer.
+    pub(crate) fn verify_layers(&self) -> Vec<LayerMismatch> {
+        let mut mismatches = Vec::new();
+        let mut queue = vec![(Path::default(), &self.root_layer)];
+        while let Some((path, layer)) = queue.pop() {
+            for (key, lower_layer) in layer.lower_layers.iter() {
+                let mismatch = |reason: &str| LayerMismatch {
+                    path: path.clone(),
+                    key: key.clone(),
+                    reason: reason.to_owned(),
+                };
+                match layer.tree.find(key) {
+                    Some(node) => match (node.value(), node.value_hash()) {
+                        (Some(value), Some(node_value_hash)) => {
+                            let expected =
+                                combine_hash(&value_hash(value), &lower_layer.tree.hash());
+                            if expected != node_value_hash {
+                                mismatches.push(mismatch(
+                                    "subtree element value hash doesn't match lower layer root \
+                                     hash",
+                                ));
+                            }
+                        }
+                        _ => mismatches.push(mismatch("subtree element has no value in proof")),
+                    },
+                    None => mismatches.push(mismatch("no subtree element for lower layer")),
+                }
+                let mut lower_path = path.clone();
+                lower_path.push(key.clone());
+                queue.push((lower_path, lower_layer));
+            }
+        }
+        mismatches
+    }
+
+    /// Build a [Tree] out of the proof to be drawn, hash only proof nodes
+    /// become ghost nodes.
+    pub(crate) fn to_tree(&self) -> Tree {
+        let mut tree = Tree::new();
+        insert_layer(&mut tree, Path::default(), &self.root_layer);
+        tree
+    }
+}
+
+fn insert_layer(tree: &mut Tree, path: Path, layer: &LayerProof) {
+    let root_key = insert_proof_tree(tree, &path, &layer.tree);
+    if let Some(subtree) = tree.subtrees.get_mut(&path) {
+        subtree.set_root(root_key);
+        subtree.set_visible(true);
+        subtree.set_expanded();
+    }
+    for (key, lower_layer) in layer.lower_layers.iter() {
+        let mut lower_path = path.clone();
+        lower_path.push(key.clone());
+        insert_layer(tree, lower_path, lower_layer);
+    }
+}
+
+/// Insert proof nodes recursively returning the key of the inserted subtree
+/// root, hash only nodes are keyed by their hash.
+fn insert_proof_tree(tree: &mut Tree, path: &Path, proof_tree: &ProofTree) -> Key {
+    let left = proof_tree
+        .left
+        .as_ref()
+        .map(|l| (insert_proof_tree(tree, path, l), l.hash()));
+    let right = proof_tree
+        .right
+        .as_ref()
+        .map(|r| (insert_proof_tree(tree, path, r), r.hash()));
+
+    let hash = proof_tree.hash();
+    let (key, mut node) = match (&proof_tree.node, proof_tree.node.value()) {
+        (ProofNode::Hash(_) | ProofNode::KVHash(_), _) => (
+            hash.to_vec(),
+            Node {
+                ghost: Some(hash),
+                ..Default::default()
+            },
+        ),
+        (ProofNode::KVDigest(key, _), _) => (
+            key.clone(),
+            Node {
+                ghost: Some(hash),
+                ..Default::default()
+            },
+        ),
+        (node, Some(value)) => {
+            let key = node.key().expect("non hash nodes have keys").clone();
+            let (element, element_flags) = decode_element(value, path, &key).unwrap_or_else(|_| {
+                (
+                    Element::Item {
+                        value: value.to_vec(),
+                    },
+                    None,
+                )
+            });
+            let feature_type = match node {
+                ProofNode::KVValueHashFeatureType(.., feature_type) => Some(*feature_type),
+                _ => None,
+            };
+            (
+                key,
+                Node {
+                    element_flags,
+                    feature_type,
+                    ..Node::new_element(element)
+                },
+            )
+        }
+        (_, None) => unreachable!("only hash and digest nodes have no value"),
+    };
+
+    node.value_hash = proof_tree.node.value_hash();
+    node.kv_digest_hash = proof_tree.node.kv_hash();
+    {
+        let mut ui_state = node.ui_state.borrow_mut();
+        ui_state.show_left = true;
+        ui_state.show_right = true;
+    }
+    if let Some((left_key, left_hash)) = left {
+        node.left_child = Some(left_key);
+        node.left_merk_hash = Some(left_hash);
+    }
+    if let Some((right_key, right_hash)) = right {
+        node.right_child = Some(right_key);
+        node.right_merk_hash = Some(right_hash);
+    }
+
+    tree.insert(path.clone(), key.clone(), node);
+    key
+}
+
+/// Decode a bincode serialized GroveDB element, element flags are serialized
+/// last and returned separately.
+pub(crate) fn decode_element(
+    bytes: &[u8],
+    path: &Path,
+    key: &[u8],
+) -> Result<(Element, Option<Vec<u8>>), ProofError> {
+    let mut reader = Reader { bytes };
+    let element = match reader.varint()? {
+        0 => grovedbg_types::Element::Item {
+            value: reader.bytes()?,
+            element_flags: None,
+        },
+        1 => reader.reference()?,
+        2 => grovedbg_types::Element::Subtree {
+            root_key: reader.option(Reader::bytes)?,
+            element_flags: None,
+        },
+        3 => grovedbg_types::Element::SumItem {
+            value: reader.signed_varint()?,
+            element_flags: None,
+        },
+        4 => grovedbg_types::Element::Sumtree {
+            root_key: reader.option(Reader::bytes)?,
+            sum: reader.signed_varint()?,
+            element_flags: None,
+        },
+        variant => {
+            return Err(ProofError::UnknownVariant {
+                what: "element",
+                variant,
+            })
+        }
+    };
+    let element_flags = reader.option(Reader::bytes)?;
+
+    let element = ElementCtx { element, path, key }
+        .try_into()
+        .map_err(|_| ProofError::ReferenceWithoutKey)?;
+    Ok((element, element_flags))
+}
+
+/// Reader of bincode's standard big endian encoding and Merk proof encoding
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], ProofError> {
+        if self.bytes.len() < n {
+            return Err(ProofError::UnexpectedEnd);
+        }
+        let (head, tail) = self.bytes.split_at(n);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> Result<u8, ProofError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], ProofError> {
+        Ok(self.take(N)?.try_into().expect("length checked"))
+    }
+
+    /// Bincode varint: single byte below 251, otherwise a marker of the
+    /// following integer width
+    fn varint(&mut self) -> Result<u64, ProofError> {
+        Ok(match self.u8()? {
+            b @ 0..=250 => b as u64,
+            251 => u16::from_be_bytes(self.array()?) as u64,
+            252 => u32::from_be_bytes(self.array()?) as u64,
+            253 => u64::from_be_bytes(self.array()?),
+            _ => return Err(ProofError::BadVarint),
+        })
+    }
+
+    fn signed_varint(&mut self) -> Result<i64, ProofError> {
+        let n = self.varint()?;
+        Ok(((n >> 1) as i64) ^ -((n & 1) as i64))
+    }
+
+    fn bytes(&mut self) -> Result<Vec<u8>, ProofError> {
+        let len = self.varint()? as usize;
+        Ok(self.take(len)?.to_vec())
+    }
+
+    fn path(&mut self) -> Result<Vec<Vec<u8>>, ProofError> {
+        let len = self.varint()?;
+        (0..len).map(|_| self.bytes()).collect()
+    }
+
+    fn option<T>(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<T, ProofError>,
+    ) -> Result<Option<T>, ProofError> {
+        match self.u8()? {
+            0 => Ok(None),
+            1 => f(self).map(Some),
+            variant => Err(ProofError::UnknownVariant {
+                what: "option",
+                variant: variant as u64,
+            }),
+        }
+    }
+
+    fn reference(&mut self) -> Result<grovedbg_types::Element, ProofError> {
+        let reference = match self.varint()? {
+            0 => grovedbg_types::Element::AbsolutePathReference {
+                path: self.path()?,
+                element_flags: None,
+            },
+            1 => grovedbg_types::Element::UpstreamRootHeightReference {
+                n_keep: self.u8()? as u32,
+                path_append: self.path()?,
+                element_flags: None,
+            },
+            2 => grovedbg_types::Element::UpstreamFromElementHeightReference {
+                n_remove: self.u8()? as u32,
+                path_append: self.path()?,
+                element_flags: None,
+            },
+            3 => grovedbg_types::Element::CousinReference {
+                swap_parent: self.bytes()?,
+                element_flags: None,
+            },
+            4 => grovedbg_types::Element::RemovedCousinReference {
+                swap_parent: self.path()?,
+                element_flags: None,
+            },
+            5 => grovedbg_types::Element::SiblingReference {
+                sibling_key: self.bytes()?,
+                element_flags: None,
+            },
+            variant => {
+                return Err(ProofError::UnknownVariant {
+                    what: "reference",
+                    variant,
+                })
+            }
+        };
+        // Max hops
+        self.option(Reader::u8)?;
+        Ok(reference)
+    }
+
+    fn layer(&mut self) -> Result<LayerProof, ProofError> {
+        let merk_proof = self.bytes()?;
+        let tree = execute(decode_ops(&merk_proof)?)?;
+        let n_lower_layers = self.varint()?;
+        let lower_layers = (0..n_lower_layers)
+            .map(|_| Ok((self.bytes()?, self.layer()?)))
+            .collect::<Result<_, ProofError>>()?;
+        Ok(LayerProof { tree, lower_layers })
+    }
+
+    /// Merk proof node encoding: one byte key length and two bytes big endian
+    /// value length
+    fn proof_node(&mut self, tag: u8) -> Result<ProofNode, ProofError> {
+        let key = |r: &mut Self| {
+            let len = r.u8()? as usize;
+            Ok::<_, ProofError>(r.take(len)?.to_vec())
+        };
+        let value = |r: &mut Self| {
+            let len = u16::from_be_bytes(r.array()?) as usize;
+            Ok::<_, ProofError>(r.take(len)?.to_vec())
+        };
+        Ok(match tag {
+            0x01 => ProofNode::Hash(self.array()?),
+            0x02 => ProofNode::KVHash(self.array()?),
+            0x03 => ProofNode::KV(key(self)?, value(self)?),
+            0x04 => ProofNode::KVValueHash(key(self)?, value(self)?, self.array()?),
+            0x05 => ProofNode::KVDigest(key(self)?, self.array()?),
+            0x06 => ProofNode::KVRefValueHash(key(self)?, value(self)?, self.array()?),
+            0x07 => {
+                let (key, value, value_hash) = (key(self)?, value(self)?, self.array()?);
+                let feature_type = match self.u8()? {
+                    0 => FeatureType::Basic,
+                    1 => FeatureType::Summed(self.leb128_signed()?),
+                    variant => {
+                        return Err(ProofError::UnknownVariant {
+                            what: "feature type",
+                            variant: variant as u64,
+                        })
+                    }
+                };
+                ProofNode::KVValueHashFeatureType(key, value, value_hash, feature_type)
+            }
+            tag => return Err(ProofError::UnknownOp(tag)),
+        })
+    }
+
+    /// Zigzag LEB128 varint used by Merk encoding
+    fn leb128_signed(&mut self) -> Result<i64, ProofError> {
+        let mut n = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.u8()?;
+            n |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(((n >> 1) as i64) ^ -((n & 1) as i64));
+            }
+        }
+        Err(ProofError::BadVarint)
+    }
+}
+
+fn decode_ops(bytes: &[u8]) -> Result<Vec<Op>, ProofError> {
+    let mut reader = Reader { bytes };
+    let mut ops = Vec::new();
+    while !reader.bytes.is_empty() {
+        let op = match reader.u8()? {
+            tag @ 0x01..=0x07 => Op::Push(reader.proof_node(tag)?),
+            tag @ 0x08..=0x0e => Op::PushInverted(reader.proof_node(tag - 0x07)?),
+            0x10 => Op::Parent,
+            0x11 => Op::Child,
+            0x12 => Op::ParentInverted,
+            0x13 => Op::ChildInverted,
+            tag => return Err(ProofError::UnknownOp(tag)),
+        };
+        ops.push(op);
+    }
+    Ok(ops)
+}
+
+fn execute(ops: Vec<Op>) -> Result<ProofTree, ProofError> {
+    let mut stack: Vec<ProofTree> = Vec::new();
+    let pop = |stack: &mut Vec<ProofTree>| stack.pop().ok_or(ProofError::BadStack);
+
+    for op in ops {
+        match op {
+            Op::Push(node) | Op::PushInverted(node) => stack.push(ProofTree {
+                node,
+                left: None,
+                right: None,
+            }),
+            Op::Parent | Op::ParentInverted => {
+                let mut parent = pop(&mut stack)?;
+                let child = pop(&mut stack)?;
+                parent.attach(matches!(op, Op::Parent), child)?;
+                stack.push(parent);
+            }
+            Op::Child | Op::ChildInverted => {
+                let child = pop(&mut stack)?;
+                let mut parent = pop(&mut stack)?;
+                parent.attach(matches!(op, Op::ChildInverted), child)?;
+                stack.push(parent);
+            }
+        }
+    }
+
+    let tree = pop(&mut stack)?;
+    if stack.is_empty() {
+        Ok(tree)
+    } else {
+        Err(ProofError::BadStack)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn push_kv(buf: &mut Vec<u8>, key: &[u8], value: &[u8]) {
+        buf.push(0x03);
+        buf.push(key.len() as u8);
+        buf.extend(key);
+        buf.extend((value.len() as u16).to_be_bytes());
+        buf.extend(value);
+    }
+
+    fn item_bytes(value: &[u8]) -> Vec<u8> {
+        let mut buf = vec![0, value.len() as u8];
+        buf.extend(value);
+        buf.push(0);
+        buf
+    }
+
+    fn sample_proof() -> Vec<u8> {
+        // b
+        // ├── a
+        // └── <hash>
+        let mut merk_proof = Vec::new();
+        push_kv(&mut merk_proof, b"a", &item_bytes(b"value_a"));
+        push_kv(&mut merk_proof, b"b", &item_bytes(b"value_b"));
+        merk_proof.push(0x10);
+        merk_proof.push(0x01);
+        merk_proof.extend([5; 32]);
+        merk_proof.push(0x11);
+
+        let mut proof = vec![0, merk_proof.len() as u8];
+        proof.extend(merk_proof);
+        proof.push(0); // no lower layers
+        proof.push(0); // prove options
+        proof
+    }
+
+    #[test]
+    fn decode_and_hash_proof() {
+        let proof = GroveDbProof::decode(&sample_proof()).unwrap();
+
+        let left_hash = node_hash(
+            &kv_digest_to_kv_hash(b"a", &value_hash(&item_bytes(b"value_a"))),
+            &NULL_HASH,
+            &NULL_HASH,
+        );
+        let expected = node_hash(
+            &kv_digest_to_kv_hash(b"b", &value_hash(&item_bytes(b"value_b"))),
+            &left_hash,
+            &[5; 32],
+        );
+        assert_eq!(proof.root_hash(), expected);
+        assert!(proof.verify_layers().is_empty());
+    }
+
+    #[test]
+    fn proof_to_tree() {
+        let tree = GroveDbProof::decode(&sample_proof()).unwrap().to_tree();
+        let subtree = tree.subtrees.get(&Path::default()).unwrap();
+
+        let root = subtree.root_node().unwrap();
+        assert_eq!(
+            root.element,
+            Element::Item {
+                value: b"value_b".to_vec()
+            }
+        );
+        assert_eq!(root.left_child.as_deref(), Some(b"a".as_ref()));
+        assert_eq!(root.right_child.as_deref(), Some([5; 32].as_ref()));
+        assert_eq!(
+            subtree.nodes.get([5; 32].as_ref()).unwrap().ghost,
+            Some([5; 32])
+        );
+        assert_eq!(
+            subtree.root_hash(),
+            Some(GroveDbProof::decode(&sample_proof()).unwrap().root_hash())
+        );
+    }
+
+    #[test]
+    fn element_flags_are_decoded() {
+        let bytes = [3, 84, 1, 2, 0xab, 0xcd];
+        let (element, flags) = decode_element(&bytes, &Path::default(), b"key").unwrap();
+        assert_eq!(element, Element::SumItem { value: 42 });
+        assert_eq!(flags, Some(vec![0xab, 0xcd]));
+    }
+
+    #[test]
+    fn bad_stack_is_an_error() {
+        let mut proof = vec![0, 1, 0x10, 0, 0];
+        assert_eq!(GroveDbProof::decode(&proof), Err(ProofError::BadStack));
+        proof[2] = 0x42;
+        assert_eq!(
+            GroveDbProof::decode(&proof),
+            Err(ProofError::UnknownOp(0x42))
+        );
+    }
+}
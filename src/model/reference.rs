@@ -0,0 +1,273 @@
+//! Detection of references with missing or invalid targets and the index of
+//! references by their targets.
+
+use std::{collections::BTreeMap, fmt};
+
+use super::{Element, Key, KeySlice, Path, ReferrersIndex, Tree};
+
+/// What a reference target turned out to be
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TargetStatus {
+    /// Target is an item, a sum item or another reference
+    Valid,
+    /// Target is a subtree element, which references must not point to
+    Subtree,
+    /// Target subtree exists but has no such key
+    MissingKey,
+    /// Target subtree doesn't exist
+    MissingPath,
+}
+
+impl TargetStatus {
+    pub(crate) fn is_broken(&self) -> bool {
+        !matches!(self, TargetStatus::Valid)
+    }
+
+    pub(crate) fn of_element(element: &Element) -> Self {
+        match element {
+            Element::Subtree { .. } | Element::Sumtree { .. } | Element::SubtreePlaceholder => {
+                TargetStatus::Subtree
+            }
+            _ => TargetStatus::Valid,
+        }
+    }
+}
+
+impl fmt::Display for TargetStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TargetStatus::Valid => write!(f, "Target exists"),
+            TargetStatus::Subtree => write!(f, "Target is a subtree element"),
+            TargetStatus::MissingKey => write!(f, "Target key doesn't exist"),
+            TargetStatus::MissingPath => write!(f, "Target path doesn't exist"),
+        }
+    }
+}
+
+impl Tree {
+    /// Status of a reference's target, `None` if the node is not a reference
+    /// or its target is not resolved yet.
+    pub(crate) fn reference_status(&self, path: &Path, key: KeySlice) -> Option<TargetStatus> {
+        let Element::Reference {
+            path: target_path,
+            key: target_key,
+        } = &self.get_node(path, key)?.element
+        else {
+            return None;
+        };
+        self.target_status(target_path, target_key)
+    }
+
+    /// Loaded nodes are used to resolve a target first, fetched statuses are
+    /// used otherwise.
+    fn target_status(&self, path: &Path, key: KeySlice) -> Option<TargetStatus> {
+        self.get_node(path, key)
+            .map(|node| TargetStatus::of_element(&node.element))
+            .or_else(|| {
+                self.reference_targets
+                    .get(&(path.clone(), key.to_vec()))
+                    .copied()
+            })
+    }
+
+    /// Pick up to `limit` targets of loaded references that are neither
+    /// resolved nor waiting for resolution, marking them as pending.
+    pub(crate) fn take_unresolved_targets(&mut self, limit: usize) -> Vec<(Path, Key)> {
+        let mut targets = Vec::new();
+        'subtrees: for subtree in self.subtrees.values() {
+            for node in subtree.nodes.values() {
+                if targets.len() >= limit {
+                    break 'subtrees;
+                }
+                let Element::Reference { path, key } = &node.element else {
+                    continue;
+                };
+                let target = (path.clone(), key.clone());
+                if self.target_status(path, key).is_none()
+                    && !self.pending_targets.contains(&target)
+                    && !targets.contains(&target)
+                {
+                    targets.push(target);
+                }
+            }
+        }
+        self.pending_targets.extend(targets.iter().cloned());
+        targets
+    }
+
+    pub(crate) fn set_target_status(&mut self, path: Path, key: Key, status: TargetStatus) {
+        self.pending_targets.remove(&(path.clone(), key.clone()));
+        self.reference_targets.insert((path, key), status);
+    }
+
+    /// Drop the fetched status of a target which node was loaded or unloaded,
+    /// it's resolved again if it's gone.
+    pub(super) fn forget_target_status(&mut self, path: &Path, key: KeySlice) {
+        self.reference_targets.remove(&(path.clone(), key.to_vec()));
+    }
+
+    /// Forget fetched targets statuses to have them resolved again, targets
+    /// still in flight are kept pending to not send them twice.
+    pub(crate) fn reset_reference_targets(&mut self) {
+        self.reference_targets.clear();
+    }
+
+    pub(crate) fn pending_targets_count(&self) -> usize {
+        self.pending_targets.len()
+    }
+
+    /// Loaded references that point to the node, the index is built lazily.
+    pub(crate) fn referrers(&self, path: &Path, key: KeySlice) -> Vec<(Path, Key)> {
+        self.referrers
+            .borrow_mut()
+            .get_or_insert_with(|| self.build_referrers_index())
+            .get(&(path.clone(), key.to_vec()))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Drop the referrers index, shall be called on every nodes update.
+    pub(super) fn invalidate_referrers(&self) {
+        *self.referrers.borrow_mut() = None;
+    }
+
+    fn build_referrers_index(&self) -> ReferrersIndex {
+        let mut index: BTreeMap<_, Vec<_>> = BTreeMap::new();
+        for (path, subtree) in self.subtrees.iter() {
+            for (key, node) in subtree.nodes.iter() {
+                if let Element::Reference {
+                    path: target_path,
+                    key: target_key,
+                } = &node.element
+                {
+                    index
+                        .entry((target_path.clone(), target_key.clone()))
+                        .or_default()
+                        .push((path.clone(), key.clone()));
+                }
+            }
+        }
+        index
+    }
+
+    /// All loaded references with broken targets.
+    pub(crate) fn broken_references(&self) -> Vec<(Path, Key, TargetStatus)> {
+        self.subtrees
+            .iter()
+            .flat_map(|(path, subtree)| {
+                subtree.nodes.keys().filter_map(|key| {
+                    self.reference_status(path, key)
+                        .filter(TargetStatus::is_broken)
+                        .map(|status| (path.clone(), key.clone(), status))
+                })
+            })
+            .collect()
+    }
+
+    /// Number of loaded references with broken targets in the subtree.
+    pub(crate) fn broken_references_count(&self, path: &Path) -> usize {
+        self.subtrees
+            .get(path)
+            .map(|subtree| {
+                subtree
+                    .nodes
+                    .keys()
+                    .filter(|key| {
+                        self.reference_status(path, key)
+                            .map(|status| status.is_broken())
+                            .unwrap_or_default()
+                    })
+                    .count()
+            })
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Node;
+
+    #[test]
+    fn references_resolution() {
+        let mut tree = Tree::new();
+        let target_path: Path = vec![b"items".to_vec()].into();
+        tree.insert(target_path.clone(), b"a".to_vec(), Node::new_item(vec![]));
+        tree.insert(
+            Path::default(),
+            b"ref_a".to_vec(),
+            Node::new_reference(target_path.clone(), b"a".to_vec()),
+        );
+        tree.insert(
+            Path::default(),
+            b"ref_items".to_vec(),
+            Node::new_reference(Path::default(), b"items".to_vec()),
+        );
+        tree.insert(
+            Path::default(),
+            b"ref_b".to_vec(),
+            Node::new_reference(target_path.clone(), b"b".to_vec()),
+        );
+
+        assert_eq!(
+            tree.reference_status(&Path::default(), b"ref_a"),
+            Some(TargetStatus::Valid)
+        );
+        assert_eq!(
+            tree.reference_status(&Path::default(), b"ref_items"),
+            Some(TargetStatus::Subtree)
+        );
+        assert_eq!(tree.reference_status(&Path::default(), b"ref_b"), None);
+
+        let unresolved = vec![(target_path.clone(), b"b".to_vec())];
+        assert_eq!(tree.take_unresolved_targets(10), unresolved);
+        assert!(tree.take_unresolved_targets(10).is_empty());
+        tree.reset_reference_targets();
+        assert!(tree.take_unresolved_targets(10).is_empty());
+
+        tree.set_target_status(target_path.clone(), b"b".to_vec(), TargetStatus::MissingKey);
+        assert_eq!(tree.pending_targets_count(), 0);
+        assert_eq!(tree.broken_references_count(&Path::default()), 2);
+        assert_eq!(tree.broken_references().len(), 2);
+
+        // Target loaded later and unloaded again is resolved anew
+        tree.insert(target_path.clone(), b"b".to_vec(), Node::new_item(vec![]));
+        tree.remove(&target_path, b"b");
+        assert_eq!(tree.reference_status(&Path::default(), b"ref_b"), None);
+        tree.insert(
+            Path::default(),
+            b"ref_c".to_vec(),
+            Node::new_reference(target_path.clone(), b"c".to_vec()),
+        );
+        assert_eq!(tree.take_unresolved_targets(1).len(), 1);
+        assert_eq!(tree.take_unresolved_targets(10).len(), 1);
+    }
+
+    #[test]
+    fn referrers_index() {
+        let mut tree = Tree::new();
+        let target_path: Path = vec![b"items".to_vec()].into();
+        tree.insert(
+            Path::default(),
+            b"ref".to_vec(),
+            Node::new_reference(target_path.clone(), b"a".to_vec()),
+        );
+        assert_eq!(
+            tree.referrers(&target_path, b"a"),
+            vec![(Path::default(), b"ref".to_vec())]
+        );
+
+        tree.insert(
+            target_path.clone(),
+            b"ref".to_vec(),
+            Node::new_reference(target_path.clone(), b"a".to_vec()),
+        );
+        assert_eq!(tree.referrers(&target_path, b"a").len(), 2);
+
+        tree.remove(&Path::default(), b"ref");
+        assert_eq!(
+            tree.referrers(&target_path, b"a"),
+            vec![(target_path.clone(), b"ref".to_vec())]
+        );
+    }
+}
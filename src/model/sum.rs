@@ -0,0 +1,149 @@
+//! Verification of sums stored in sumtree elements against loaded contents.
+
+use super::{Element, Key, KeySlice, Path, Subtree, Tree};
+
+/// Sums within this fraction of i64 bounds are considered at risk of
+/// overflow
+const OVERFLOW_RISK_DIVISOR: u64 = 10;
+
+/// Sum stored in a sumtree element and the one computed out of its loaded
+/// child subtree.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct SumVerification {
+    pub(crate) stored: i64,
+    /// Computed with wider integers to show the real value on overflow
+    pub(crate) computed: i128,
+}
+
+impl SumVerification {
+    pub(crate) fn is_consistent(&self) -> bool {
+        self.stored as i128 == self.computed
+    }
+
+    pub(crate) fn overflows(&self) -> bool {
+        i64::try_from(self.computed).is_err()
+    }
+
+    /// Either sum is close enough to i64 bounds to overflow soon
+    pub(crate) fn overflow_risk(&self) -> bool {
+        let threshold = (i64::MAX as u64 - i64::MAX as u64 / OVERFLOW_RISK_DIVISOR) as u128;
+        self.overflows()
+            || self.computed.unsigned_abs() > threshold
+            || self.stored.unsigned_abs() as u128 > threshold
+    }
+
+    pub(crate) fn has_issues(&self) -> bool {
+        !self.is_consistent() || self.overflow_risk()
+    }
+}
+
+impl Subtree {
+    /// All nodes of the subtree are loaded, so it can be summed up.
+    pub(crate) fn is_fully_loaded(&self) -> bool {
+        self.waitlist.is_empty()
+            && self.cluster_roots.is_empty()
+            && match &self.root_node {
+                Some(root) => self.nodes.contains_key(root),
+                None => self.nodes.is_empty(),
+            }
+    }
+
+    /// Sum of sum items and nested sumtrees sums.
+    fn compute_sum(&self) -> i128 {
+        self.nodes
+            .values()
+            .map(|node| match node.element {
+                Element::SumItem { value } | Element::Sumtree { sum: value, .. } => value as i128,
+                _ => 0,
+            })
+            .sum()
+    }
+}
+
+impl Tree {
+    /// Verify the sum of a sumtree element, `None` if it's not a sumtree or
+    /// its child subtree is not fully loaded.
+    pub(crate) fn verify_sum(&self, path: &Path, key: KeySlice) -> Option<SumVerification> {
+        let Element::Sumtree { root_key, sum } = &self.get_node(path, key)?.element else {
+            return None;
+        };
+        let mut child_path = path.clone();
+        child_path.push(key.to_vec());
+        let computed = match (root_key, self.subtrees.get(&child_path)) {
+            (None, _) => 0,
+            (Some(_), Some(child)) if child.is_fully_loaded() => child.compute_sum(),
+            _ => return None,
+        };
+        Some(SumVerification {
+            stored: *sum,
+            computed,
+        })
+    }
+
+    /// Verifications of all loaded sumtrees that have mismatches or overflow
+    /// risks.
+    pub(crate) fn sum_issues(&self) -> Vec<(Path, Key, SumVerification)> {
+        self.subtrees
+            .iter()
+            .flat_map(|(path, subtree)| {
+                subtree.nodes.keys().filter_map(|key| {
+                    self.verify_sum(path, key)
+                        .filter(SumVerification::has_issues)
+                        .map(|v| (path.clone(), key.clone(), v))
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Node;
+
+    fn sum_tree(stored: i64, items: &[i64]) -> Tree {
+        let mut tree = Tree::new();
+        tree.set_root(b"sum".to_vec());
+        tree.insert(
+            Path::default(),
+            b"sum".to_vec(),
+            Node::new_sumtree(Some(vec![0]), stored),
+        );
+        let path: Path = vec![b"sum".to_vec()].into();
+        for (i, value) in items.iter().enumerate() {
+            let mut node = Node::new_sum_item(*value);
+            if i + 1 < items.len() {
+                node = node.with_right_child(vec![i as u8 + 1]);
+            }
+            tree.insert(path.clone(), vec![i as u8], node);
+        }
+        tree
+    }
+
+    #[test]
+    fn sums_verification() {
+        let tree = sum_tree(5, &[2, 3]);
+        let verification = tree.verify_sum(&Path::default(), b"sum").unwrap();
+        assert!(verification.is_consistent());
+        assert!(!verification.overflow_risk());
+
+        let tree = sum_tree(4, &[2, 3]);
+        assert_eq!(tree.sum_issues().len(), 1);
+
+        let tree = sum_tree(i64::MAX, &[i64::MAX, 1]);
+        let verification = tree.verify_sum(&Path::default(), b"sum").unwrap();
+        assert!(!verification.is_consistent());
+        assert!(verification.overflows());
+    }
+
+    #[test]
+    fn partially_loaded_sumtree_is_not_verified() {
+        let mut tree = sum_tree(5, &[2]);
+        tree.insert(
+            vec![b"sum".to_vec()].into(),
+            vec![0],
+            Node::new_sum_item(2).with_left_child(vec![]),
+        );
+        assert_eq!(tree.verify_sum(&Path::default(), b"sum"), None);
+    }
+}
@@ -1,32 +1,36 @@
+mod camera;
 mod common;
+mod contracts;
+mod download;
+mod export;
+mod hex_dump;
+mod inspector;
+mod integrity;
+mod keyboard;
+mod legend;
+mod listing_filter;
+mod minimap;
 mod node;
+mod path_query;
+mod proof;
+mod reference_edges;
+mod references;
+mod theme;
 mod tree;
 
-pub(crate) use common::DisplayVariant;
-use eframe::egui;
-use strum::IntoEnumIterator;
-pub(crate) use tree::TreeDrawer;
-
-use self::node::element_to_color;
-use crate::model::Element;
-
-pub(crate) fn draw_legend(ui: &mut egui::Ui) {
-    egui::Area::new(egui::Id::new("legend"))
-        .anchor(egui::Align2::RIGHT_TOP, [-20.0, 50.0])
-        .order(egui::Order::Foreground)
-        .show(ui.ctx(), |ui| {
-            egui::Frame::default()
-                .rounding(egui::Rounding::same(4.0))
-                .inner_margin(egui::Margin::same(8.0))
-                .stroke(ui.ctx().style().visuals.window_stroke)
-                .fill(ui.style().visuals.panel_fill)
-                .show(ui, |ui| {
-                    ui.style_mut().wrap = Some(false);
-                    Element::iter().for_each(|element| {
-                        ui.label(
-                            egui::RichText::new(element.as_ref()).color(element_to_color(&element)),
-                        );
-                    });
-                });
-        });
-}
+pub(crate) use camera::{CameraAnimation, CameraFocus};
+pub(crate) use common::{bytes_as_hex, bytes_from_display_variant, DisplayVariant};
+pub(crate) use contracts::ContractsWindow;
+pub(crate) use export::ExportWindow;
+pub(crate) use hex_dump::HexDumpState;
+pub(crate) use inspector::draw_inspector;
+pub(crate) use integrity::IntegrityWindow;
+pub(crate) use keyboard::{handle_keyboard, KEY_BINDINGS_HINT};
+pub(crate) use legend::draw_legend;
+pub(crate) use minimap::draw_minimap;
+pub(crate) use path_query::PathQueryWindow;
+pub(crate) use proof::ProofViewer;
+pub(crate) use reference_edges::ReferenceEdgesMode;
+pub(crate) use references::ReferencesWindow;
+pub(crate) use theme::{Theme, ThemeWindow, THEME_STORAGE_KEY};
+pub(crate) use tree::{TreeDrawer, TreeViewOptions};
@@ -0,0 +1,107 @@
+//! Animated canvas transitions to focus on parts of the tree
+
+use eframe::{
+    emath::TSTransform,
+    epaint::{Pos2, Rect},
+};
+
+/// Seconds a transition takes
+const ANIMATION_DURATION: f64 = 0.4;
+/// Share of the canvas taken by a fitted rect
+const FIT_PADDING: f32 = 0.9;
+const MIN_SCALING: f32 = 0.02;
+const MAX_SCALING: f32 = 2.0;
+
+/// What to show in the middle of the canvas
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum CameraFocus {
+    /// Zoom to fit the rect into the canvas
+    Fit(Rect),
+    /// Move the point to the center keeping the zoom
+    Center(Pos2),
+}
+
+impl CameraFocus {
+    /// Transform of layers coordinates into screen coordinates to show the
+    /// focus on the canvas.
+    pub(crate) fn transform(&self, current: TSTransform, canvas: Rect) -> TSTransform {
+        let (center, scaling) = match self {
+            CameraFocus::Fit(rect) => (
+                rect.center(),
+                ((canvas.width() / rect.width().max(1.))
+                    .min(canvas.height() / rect.height().max(1.))
+                    * FIT_PADDING)
+                    .clamp(MIN_SCALING, MAX_SCALING),
+            ),
+            CameraFocus::Center(point) => (*point, current.scaling),
+        };
+        TSTransform::new(
+            canvas.center().to_vec2() - center.to_vec2() * scaling,
+            scaling,
+        )
+    }
+}
+
+/// Transition between two transforms with the canvas center moving straight
+/// and zoom changing evenly.
+pub(crate) struct CameraAnimation {
+    from: TSTransform,
+    to: TSTransform,
+    start: f64,
+}
+
+impl CameraAnimation {
+    pub(crate) fn new(from: TSTransform, to: TSTransform, now: f64) -> Self {
+        CameraAnimation {
+            from,
+            to,
+            start: now,
+        }
+    }
+
+    /// Transform at the moment and whether the animation is finished.
+    pub(crate) fn transform_at(&self, now: f64, canvas: Rect) -> (TSTransform, bool) {
+        let t = ((now - self.start) / ANIMATION_DURATION).clamp(0., 1.) as f32;
+        // Ease in and out
+        let eased = t * t * (3. - 2. * t);
+
+        let center_of = |transform: &TSTransform| (transform.inverse() * canvas.center()).to_vec2();
+        let center = center_of(&self.from) + (center_of(&self.to) - center_of(&self.from)) * eased;
+        let scaling = self.from.scaling * (self.to.scaling / self.from.scaling).powf(eased);
+        (
+            TSTransform::new(canvas.center().to_vec2() - center * scaling, scaling),
+            t >= 1.,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use eframe::epaint::Vec2;
+
+    use super::*;
+
+    fn assert_close(a: Pos2, b: Pos2) {
+        assert!(a.distance(b) < 1e-3, "{a:?} != {b:?}");
+    }
+
+    #[test]
+    fn focus_transforms() {
+        let canvas = Rect::from_min_size(Pos2::ZERO, Vec2::new(1000., 500.));
+        let rect = Rect::from_min_size(Pos2::new(100., 100.), Vec2::new(800., 100.));
+
+        let fit = CameraFocus::Fit(rect).transform(TSTransform::default(), canvas);
+        assert_eq!(fit.scaling, 1.25 * FIT_PADDING);
+        assert_close(fit * rect.center(), canvas.center());
+
+        let center = CameraFocus::Center(rect.min).transform(fit, canvas);
+        assert_eq!(center.scaling, fit.scaling);
+        assert_close(center * rect.min, canvas.center());
+
+        let animation = CameraAnimation::new(TSTransform::default(), fit, 0.);
+        let (end, finished) = animation.transform_at(ANIMATION_DURATION, canvas);
+        assert!(finished);
+        assert_close(end * rect.center(), canvas.center());
+        assert!(!animation.transform_at(ANIMATION_DURATION / 2., canvas).1);
+    }
+}
@@ -2,10 +2,12 @@
 
 use std::fmt::Write;
 
+use base64::Engine;
 use eframe::{
     egui::{self, Label, Response, RichText, Sense},
     epaint::Color32,
 };
+use strum::IntoEnumIterator;
 
 use crate::model::Path;
 
@@ -38,6 +40,26 @@ pub(crate) fn bytes_as_hex(bytes: &[u8]) -> String {
     }
 }
 
+/// Node location with segments shown as hex
+pub(crate) fn path_with_key(path: &Path, key: &[u8]) -> String {
+    let segments: Vec<_> = path.iter().map(|segment| bytes_as_hex(segment)).collect();
+    format!("[{}] {}", segments.join(", "), bytes_as_hex(key))
+}
+
+pub(crate) fn bytes_as_base64(bytes: &[u8]) -> String {
+    base64::engine::general_purpose::STANDARD.encode(bytes)
+}
+
+/// Byte string literal that can be pasted into Rust code
+pub(crate) fn bytes_as_rust_literal(bytes: &[u8]) -> String {
+    let mut buf = String::from("b\"");
+    bytes
+        .iter()
+        .for_each(|b| buf.extend(std::ascii::escape_default(*b).map(char::from)));
+    buf.push('"');
+    buf
+}
+
 pub(crate) fn bytes_by_display_variant(bytes: &[u8], display_variant: &DisplayVariant) -> String {
     match display_variant {
         DisplayVariant::U8 => bytes_as_slice(bytes),
@@ -46,6 +68,29 @@ pub(crate) fn bytes_by_display_variant(bytes: &[u8], display_variant: &DisplayVa
     }
 }
 
+/// Parse user input back into bytes according to the display variant, `None`
+/// if the input is malformed.
+pub(crate) fn bytes_from_display_variant(
+    text: &str,
+    display_variant: DisplayVariant,
+) -> Option<Vec<u8>> {
+    match display_variant {
+        DisplayVariant::U8 => text
+            .trim()
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|s| !s.is_empty())
+            .map(|s| s.parse().ok())
+            .collect(),
+        DisplayVariant::String => Some(text.as_bytes().to_vec()),
+        DisplayVariant::Hex => {
+            let text = text.trim();
+            hex::decode(text.strip_prefix("0x").unwrap_or(text)).ok()
+        }
+    }
+}
+
 /// Represent binary data different ways and to choose from
 pub(crate) fn binary_label_colored<'a>(
     ui: &mut egui::Ui,
@@ -65,13 +110,29 @@ fn display_variant_dropdown<'a>(
 ) -> Response {
     let response = ui.add(Label::new(RichText::new(text).color(color)).sense(Sense::click()));
     response.context_menu(|menu| {
-        menu.radio_value(display_variant, DisplayVariant::U8, "Integers");
-        menu.radio_value(display_variant, DisplayVariant::String, "UTF-8 String");
-        menu.radio_value(display_variant, DisplayVariant::Hex, "Hex String");
+        DisplayVariant::iter().for_each(|variant| {
+            menu.radio_value(display_variant, variant, variant.name());
+        });
     });
     response
 }
 
+/// Combo box to pick the representation typed bytes are parsed with
+pub(crate) fn display_variant_combo(
+    ui: &mut egui::Ui,
+    id_source: impl std::hash::Hash,
+    display_variant: &mut DisplayVariant,
+) {
+    egui::ComboBox::from_id_source(id_source)
+        .width(70.)
+        .selected_text(display_variant.name())
+        .show_ui(ui, |combo| {
+            DisplayVariant::iter().for_each(|variant| {
+                combo.selectable_value(display_variant, variant, variant.name());
+            });
+        });
+}
+
 pub(crate) fn binary_label<'a>(
     ui: &mut egui::Ui,
     bytes: &[u8],
@@ -80,7 +141,7 @@ pub(crate) fn binary_label<'a>(
     binary_label_colored(ui, bytes, display_variant, Color32::GRAY)
 }
 
-#[derive(Debug, Default, PartialEq, Clone, Copy)]
+#[derive(Debug, Default, PartialEq, Clone, Copy, strum::EnumIter)]
 pub(crate) enum DisplayVariant {
     #[default]
     U8,
@@ -88,10 +149,30 @@ pub(crate) enum DisplayVariant {
     Hex,
 }
 
+impl DisplayVariant {
+    /// Name of the representation shown to user
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            DisplayVariant::U8 => "Integers",
+            DisplayVariant::String => "UTF-8",
+            DisplayVariant::Hex => "Hex",
+        }
+    }
+}
+
 pub(crate) fn path_label<'a>(
     ui: &mut egui::Ui,
     path: &'a Path,
     display_variant: &mut DisplayVariant,
+) -> egui::Response {
+    path_label_colored(ui, path, display_variant, Color32::LIGHT_GRAY)
+}
+
+pub(crate) fn path_label_colored(
+    ui: &mut egui::Ui,
+    path: &Path,
+    display_variant: &mut DisplayVariant,
+    color: Color32,
 ) -> egui::Response {
     let mut iter = path.iter();
     if let Some(key) = iter.next_back() {
@@ -107,7 +188,7 @@ pub(crate) fn path_label<'a>(
         text.push_str(&bytes_by_display_variant(key, display_variant));
         text.push_str("]");
 
-        let response = display_variant_dropdown(ui, &text, display_variant, Color32::LIGHT_GRAY);
+        let response = display_variant_dropdown(ui, &text, display_variant, color);
 
         response.on_hover_ui_at_pointer(|hover_ui| {
             let mut text = String::from("[");
@@ -0,0 +1,122 @@
+//! Data contracts loading and documents display
+
+use eframe::{
+    egui::{self, RichText},
+    epaint::Color32,
+};
+
+use super::theme::Theme;
+use crate::model::{
+    contract::{Contracts, DecodeError, Value},
+    NodeCtx,
+};
+
+/// Window to paste data contracts JSON into
+#[derive(Default)]
+pub(crate) struct ContractsWindow {
+    open: bool,
+    input: String,
+    error: Option<String>,
+}
+
+impl ContractsWindow {
+    pub(crate) fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+
+    pub(crate) fn show(&mut self, ctx: &egui::Context, contracts: &mut Contracts) {
+        let mut open = self.open;
+        egui::Window::new("Data contracts")
+            .open(&mut open)
+            .default_width(400.)
+            .show(ctx, |ui| {
+                let mut to_remove = None;
+                for contract in contracts.iter() {
+                    ui.horizontal(|line| {
+                        if line.button("🗑").clicked() {
+                            to_remove = Some(contract.id.clone());
+                        }
+                        line.label(contract.id_string());
+                        line.label(
+                            RichText::new(
+                                contract
+                                    .document_types
+                                    .keys()
+                                    .cloned()
+                                    .collect::<Vec<_>>()
+                                    .join(", "),
+                            )
+                            .color(Color32::GRAY),
+                        );
+                    });
+                }
+                if let Some(id) = to_remove {
+                    contracts.remove(&id);
+                }
+
+                ui.separator();
+                egui::ScrollArea::vertical()
+                    .max_height(300.)
+                    .show(ui, |ui| {
+                        ui.add(
+                            egui::TextEdit::multiline(&mut self.input)
+                                .hint_text("Data contract JSON")
+                                .code_editor()
+                                .desired_width(f32::INFINITY),
+                        );
+                    });
+
+                if ui.button("Load").clicked() {
+                    match contracts.load_json(&self.input) {
+                        Ok(()) => {
+                            self.input.clear();
+                            self.error = None;
+                        }
+                        Err(e) => self.error = Some(e.to_string()),
+                    }
+                }
+
+                if let Some(error) = &self.error {
+                    ui.colored_label(Color32::RED, error);
+                }
+            });
+        self.open = open;
+    }
+}
+
+/// Draw a decoded key of a documents subtree if there is a contract for it
+pub(crate) fn decoded_key_label(ui: &mut egui::Ui, contracts: &Contracts, node_ctx: NodeCtx) {
+    if let Some(value) = contracts
+        .documents_subtree(node_ctx.path())
+        .and_then(|subtree| subtree.decode_key(node_ctx.key()))
+    {
+        ui.label(RichText::new(value.to_string()).italics());
+    }
+}
+
+/// Draw a field table of a document
+pub(crate) fn document_view(
+    ui: &mut egui::Ui,
+    theme: &Theme,
+    id_source: egui::Id,
+    document: Result<Vec<(String, Value)>, DecodeError>,
+) {
+    egui::CollapsingHeader::new("Document")
+        .id_source(id_source.with("document"))
+        .show(ui, |ui| match document {
+            Ok(fields) => {
+                egui::Grid::new(id_source.with("document_fields"))
+                    .striped(true)
+                    .show(ui, |grid| {
+                        for (name, value) in fields {
+                            grid.label(RichText::new(name).color(theme.muted_text()));
+                            grid.label(value.to_string());
+                            grid.end_row();
+                        }
+                    });
+            }
+            Err(e) => {
+                ui.colored_label(theme.error(), format!("Unable to decode: {e}"));
+            }
+        });
+}
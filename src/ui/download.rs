@@ -0,0 +1,43 @@
+//! Saving exported files: a browser download in wasm, a file in the working
+//! directory otherwise
+
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum DownloadError {
+    #[cfg(target_arch = "wasm32")]
+    #[error("browser download failed: {0}")]
+    Browser(String),
+    #[cfg(not(target_arch = "wasm32"))]
+    #[error("can't write the file: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// Offer the file for download, returns where it went.
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn save_file(name: &str, mime: &str, bytes: &[u8]) -> Result<String, DownloadError> {
+    use base64::Engine;
+    use wasm_bindgen::JsCast;
+
+    let browser_error = |error: wasm_bindgen::JsValue| DownloadError::Browser(format!("{error:?}"));
+    let document = web_sys::window()
+        .and_then(|window| window.document())
+        .ok_or_else(|| DownloadError::Browser("no document".to_owned()))?;
+    let anchor: web_sys::HtmlAnchorElement = document
+        .create_element("a")
+        .map_err(browser_error)?
+        .unchecked_into();
+    anchor.set_href(&format!(
+        "data:{mime};base64,{}",
+        base64::engine::general_purpose::STANDARD.encode(bytes)
+    ));
+    anchor.set_download(name);
+    anchor.click();
+    Ok(format!("Downloaded {name}"))
+}
+
+/// Offer the file for download, returns where it went.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn save_file(name: &str, _mime: &str, bytes: &[u8]) -> Result<String, DownloadError> {
+    std::fs::write(name, bytes)?;
+    let path = std::fs::canonicalize(name).unwrap_or_else(|_| name.into());
+    Ok(format!("Saved to {}", path.display()))
+}
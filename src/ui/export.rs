@@ -0,0 +1,926 @@
+//! Export of the canvas to SVG and PNG images, nodes and edges are placed
+//! where the last drawing of the canvas put them.
+
+use std::fmt::Write;
+
+use eframe::{
+    egui::{self, FontId},
+    emath::TSTransform,
+    epaint::{
+        ClippedShape, Color32, CubicBezierShape, FontImage, Mesh, Pos2, Primitive, Rect, RectShape,
+        Rounding, Shape, Stroke, TessellationOptions, Tessellator, Vec2, Vertex,
+    },
+};
+use strum::{AsRefStr, EnumIter, IntoEnumIterator};
+
+use super::{
+    common::{bytes_as_hex, bytes_by_display_variant, path_with_key},
+    download::{save_file, DownloadError},
+    reference_edges::{arrow_tip, edge_curve, ReferenceEdgesMode},
+    TreeViewOptions,
+};
+use crate::model::{
+    alignment::{COLLAPSED_SUBTREE_WIDTH, NODE_HEIGHT, NODE_WIDTH},
+    Element, Key, Node, NodeCtx, NodeUiState, Path, SubtreeCtx, Tree,
+};
+
+const FONT_SIZE: f32 = 12.;
+const LINE_HEIGHT: f32 = 16.;
+const PADDING: f32 = 8.;
+/// Labels are cut to this number of characters to stay inside their boxes
+const MAX_LABEL_CHARS: usize = 32;
+/// Canvas margin around an exported subtree
+const SUBTREE_MARGIN: f32 = 20.;
+/// Largest side of a PNG image in pixels
+const MAX_IMAGE_SIDE: f32 = 8192.;
+/// Largest number of pixels of a PNG image, larger scenes are downscaled
+const MAX_IMAGE_PIXELS: f32 = 32e6;
+/// Pixels rasterized per frame while exporting a PNG
+const RASTER_PIXELS_PER_FRAME: usize = 2_000_000;
+/// Gamma of the font atlas coverage, the same egui renders text with
+const COVERAGE_GAMMA: f32 = 0.55;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, EnumIter, AsRefStr)]
+pub(crate) enum ImageFormat {
+    #[default]
+    #[strum(serialize = "SVG")]
+    Svg,
+    #[strum(serialize = "PNG")]
+    Png,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, EnumIter, AsRefStr)]
+pub(crate) enum ExportScope {
+    #[default]
+    #[strum(serialize = "Visible canvas")]
+    View,
+    /// Subtree of the selected node with its visible descendant subtrees
+    #[strum(serialize = "Selected subtree")]
+    SelectedSubtree,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum ExportError {
+    #[error("select a node of the subtree to export")]
+    NoSelection,
+    #[error("the subtree is not shown on the canvas")]
+    SubtreeHidden,
+    #[error("can't encode PNG: {0}")]
+    Png(#[from] png::EncodingError),
+    #[error(transparent)]
+    Download(#[from] DownloadError),
+}
+
+/// Canvas item in layer coordinates
+enum Item {
+    Line {
+        points: [Pos2; 2],
+        color: Color32,
+    },
+    Curve {
+        points: [Pos2; 4],
+        color: Color32,
+    },
+    Rect {
+        rect: Rect,
+        fill: Color32,
+        stroke: Stroke,
+    },
+    /// Line of monospace text placed by its left top corner
+    Text {
+        pos: Pos2,
+        text: String,
+        color: Color32,
+    },
+}
+
+/// Reference to draw an edge for once all subtrees are placed
+struct Reference {
+    from: Pos2,
+    direction: Vec2,
+    source: (Path, Key),
+    target: (Path, Key),
+}
+
+fn truncated(mut text: String) -> String {
+    if let Some((cut, _)) = text.char_indices().nth(MAX_LABEL_CHARS) {
+        text.truncate(cut);
+        text.push('…');
+    }
+    text
+}
+
+fn key_text(node_ctx: NodeCtx) -> String {
+    let display_variant = node_ctx.node().ui_state.borrow().key_display_variant;
+    truncated(bytes_by_display_variant(node_ctx.key(), &display_variant))
+}
+
+/// One line summary of the element's value in its chosen display variant
+fn value_text(node: &Node) -> String {
+    let display_variant = node.ui_state.borrow().item_display_variant;
+    let text = match &node.element {
+        Element::Item { value } => bytes_by_display_variant(value, &display_variant),
+        Element::SumItem { value } => format!("Value: {value}"),
+        Element::Reference { path, key } => format!("→ {}", path_with_key(path, key)),
+        Element::Sumtree { sum, .. } => format!("Sum: {sum}"),
+        Element::Subtree { .. } | Element::SubtreePlaceholder => "Subtree".to_owned(),
+    };
+    truncated(text)
+}
+
+/// Box of a node as it was drawn, nodes skipped being off-screen keep the
+/// size of their last drawing.
+fn node_box(state: &NodeUiState) -> Rect {
+    let width = state.right_sibling_point.x - state.left_sibling_point.x;
+    let height = state.output_point.y - state.input_point.y;
+    let size = Vec2::new(
+        if width > 0. { width } else { NODE_WIDTH },
+        if height > 0. { height } else { NODE_HEIGHT },
+    );
+    Rect::from_min_size(state.input_point - Vec2::new(size.x / 2., 0.), size)
+}
+
+/// Text safe to put into XML, control characters XML doesn't allow are
+/// replaced with U+FFFD.
+fn xml_escaped(text: &str) -> String {
+    text.chars().fold(String::new(), |mut escaped, c| {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c < ' ' => escaped.push(char::REPLACEMENT_CHARACTER),
+            c => escaped.push(c),
+        }
+        escaped
+    })
+}
+
+/// SVG paint attributes, such as `fill` or `stroke`, of the color
+fn svg_paint(attribute: &str, color: Color32) -> String {
+    let [r, g, b, a] = color.to_srgba_unmultiplied();
+    format!(
+        r#"{attribute}="rgb({r},{g},{b})" {attribute}-opacity="{:.3}""#,
+        a as f32 / 255.
+    )
+}
+
+/// Nodes, subtrees and edges of a part of the canvas
+struct Scene {
+    /// Exported part of the canvas in layer coordinates
+    bounds: Rect,
+    /// Image pixels per layer point
+    scale: f32,
+    background: Color32,
+    text_color: Color32,
+    /// Drawn under the boxes
+    edges: Vec<Item>,
+    boxes: Vec<Item>,
+}
+
+impl Scene {
+    /// Collect visible subtrees passing `include` with edges between them.
+    fn new(
+        tree: &Tree,
+        options: &TreeViewOptions,
+        include: impl Fn(&Path) -> bool,
+        bounds: Rect,
+        scale: f32,
+    ) -> Self {
+        let visuals = options.theme.visuals();
+        let mut scene = Scene {
+            bounds,
+            scale,
+            background: visuals.panel_fill,
+            text_color: visuals.text_color(),
+            edges: Vec::new(),
+            boxes: Vec::new(),
+        };
+
+        let mut references = Vec::new();
+        for subtree_ctx in tree
+            .iter_subtrees()
+            .filter(|ctx| ctx.subtree().visible() && include(ctx.path()))
+        {
+            if subtree_ctx.subtree().is_expanded() {
+                scene.add_expanded(subtree_ctx, options, &mut references);
+            } else {
+                scene.add_collapsed(subtree_ctx, options, &mut references);
+            }
+
+            let mut parent_path = subtree_ctx.path().clone();
+            let Some(key) = parent_path.pop() else {
+                continue;
+            };
+            let parent_out = include(&parent_path)
+                .then(|| tree.subtrees.get(&parent_path))
+                .flatten()
+                .filter(|parent| parent.visible())
+                .and_then(|parent| parent.get_node_output(&key));
+            if let (Some(from), Some(to)) =
+                (parent_out, subtree_ctx.subtree().get_subtree_input_point())
+            {
+                scene.edges.push(Item::Line {
+                    points: [from, to],
+                    color: options.theme.subtree_edge(),
+                });
+            }
+        }
+
+        for reference in references {
+            let visible = match options.reference_edges {
+                ReferenceEdgesMode::All => true,
+                ReferenceEdgesMode::SelectedNode => {
+                    tree.is_selected(&reference.source.0, &reference.source.1)
+                        || tree.is_selected(&reference.target.0, &reference.target.1)
+                }
+                ReferenceEdgesMode::Hidden => false,
+            };
+            let Some(to) = visible
+                .then(|| tree.subtrees.get(&reference.target.0))
+                .flatten()
+                .filter(|subtree| subtree.visible())
+                .and_then(|subtree| subtree.get_node_input(&reference.target.1))
+            else {
+                continue;
+            };
+            let color = options.theme.reference_edge();
+            let points = edge_curve(reference.from, reference.from, reference.direction, to);
+            scene.edges.push(Item::Curve { points, color });
+            arrow_tip(points)
+                .into_iter()
+                .for_each(|points| scene.edges.push(Item::Line { points, color }));
+        }
+
+        scene
+    }
+
+    fn add_text_lines(&mut self, rect: Rect, lines: impl IntoIterator<Item = (String, Color32)>) {
+        lines
+            .into_iter()
+            .enumerate()
+            .for_each(|(i, (text, color))| {
+                self.boxes.push(Item::Text {
+                    pos: rect.min + Vec2::new(PADDING, PADDING + i as f32 * LINE_HEIGHT),
+                    text,
+                    color,
+                })
+            });
+    }
+
+    fn add_expanded(
+        &mut self,
+        subtree_ctx: SubtreeCtx,
+        options: &TreeViewOptions,
+        references: &mut Vec<Reference>,
+    ) {
+        let subtree = subtree_ctx.subtree();
+        let theme = &options.theme;
+        for placement in subtree.expanded_layout() {
+            let Some(node_ctx) = subtree_ctx.get_node(&placement.key) else {
+                continue;
+            };
+            let node = node_ctx.node();
+            let state = node.ui_state.borrow();
+            if let Some(parent_out) = placement
+                .parent
+                .and_then(|parent| subtree.get_node_output(&parent))
+            {
+                self.edges.push(Item::Line {
+                    points: [parent_out, state.input_point],
+                    color: theme.node_edge(),
+                });
+            }
+            if !options.element_types.is_visible(&node.element) {
+                continue;
+            }
+
+            let opacity = options.element_types.opacity(&node.element);
+            let rect = node_box(&state);
+            let width = if node_ctx.is_selected() { 3. } else { 1. };
+            if let Some(hash) = &node.ghost {
+                self.boxes.push(Item::Rect {
+                    rect,
+                    fill: theme.node_fill().gamma_multiply(0.6),
+                    stroke: Stroke::new(width, theme.ghost()),
+                });
+                self.add_text_lines(rect, [(format!("# {}", bytes_as_hex(hash)), theme.ghost())]);
+                continue;
+            }
+
+            let fill = if node_ctx.is_query_result() {
+                theme.query_result_fill()
+            } else {
+                theme.node_fill()
+            };
+            let color = theme.element_color(&node.element).gamma_multiply(opacity);
+            self.boxes.push(Item::Rect {
+                rect,
+                fill: fill.gamma_multiply(opacity),
+                stroke: Stroke::new(width, color),
+            });
+            self.add_text_lines(
+                rect,
+                [
+                    (key_text(node_ctx), color),
+                    (value_text(node), self.text_color.gamma_multiply(opacity)),
+                ],
+            );
+
+            if let Element::Reference { path, key } = &node.element {
+                references.push(Reference {
+                    from: state.output_point,
+                    direction: Vec2::DOWN,
+                    source: (subtree_ctx.path().clone(), placement.key.clone()),
+                    target: (path.clone(), key.clone()),
+                });
+            }
+        }
+    }
+
+    fn add_collapsed(
+        &mut self,
+        subtree_ctx: SubtreeCtx,
+        options: &TreeViewOptions,
+        references: &mut Vec<Reference>,
+    ) {
+        let subtree = subtree_ctx.subtree();
+        let theme = &options.theme;
+        let display_variant = *subtree.path_display_variant_mut();
+        let title = truncated(
+            subtree_ctx
+                .path()
+                .iter()
+                .map(|segment| bytes_by_display_variant(segment, &display_variant))
+                .collect::<Vec<_>>()
+                .join("/"),
+        );
+
+        let mut lines = vec![(format!("/{title}"), self.text_color)];
+        for node_ctx in subtree_ctx
+            .iter_listed_nodes(options.page_size)
+            .filter(|node_ctx| options.element_types.is_visible(&node_ctx.node().element))
+        {
+            let node = node_ctx.node();
+            let opacity = options.element_types.opacity(&node.element);
+            lines.push((
+                key_text(node_ctx),
+                theme.element_color(&node.element).gamma_multiply(opacity),
+            ));
+            lines.push((
+                format!("  {}", value_text(node)),
+                self.text_color.gamma_multiply(opacity),
+            ));
+
+            if let Element::Reference { path, key } = &node.element {
+                if subtree_ctx.path() != path {
+                    references.push(Reference {
+                        from: subtree.get_subtree_output_point(),
+                        direction: Vec2::DOWN,
+                        source: (subtree_ctx.path().clone(), node_ctx.key().to_vec()),
+                        target: (path.clone(), key.clone()),
+                    });
+                }
+            }
+        }
+
+        let top = subtree
+            .get_subtree_input_point()
+            .unwrap_or(subtree.get_subtree_output_point());
+        let height = (subtree.get_subtree_output_point().y - top.y)
+            .max(2. * PADDING + lines.len() as f32 * LINE_HEIGHT);
+        let rect = Rect::from_min_size(
+            top - Vec2::new(COLLAPSED_SUBTREE_WIDTH / 2., 0.),
+            Vec2::new(COLLAPSED_SUBTREE_WIDTH, height),
+        );
+        self.boxes.push(Item::Rect {
+            rect,
+            fill: theme.node_fill(),
+            stroke: Stroke::new(1., theme.frame_stroke()),
+        });
+        self.add_text_lines(rect, lines);
+    }
+
+    fn items(&self) -> impl Iterator<Item = &Item> {
+        self.edges.iter().chain(self.boxes.iter())
+    }
+
+    fn to_svg(&self) -> String {
+        let size = self.bounds.size() * self.scale;
+        let min = self.bounds.min;
+        let mut svg = String::new();
+        let _ = writeln!(
+            svg,
+            concat!(
+                r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}""#,
+                r#" viewBox="{} {} {} {}" font-family="monospace" font-size="{}">"#
+            ),
+            size.x,
+            size.y,
+            min.x,
+            min.y,
+            self.bounds.width(),
+            self.bounds.height(),
+            FONT_SIZE,
+        );
+        let _ = writeln!(
+            svg,
+            r#"<rect x="{}" y="{}" width="{}" height="{}" {}/>"#,
+            min.x,
+            min.y,
+            self.bounds.width(),
+            self.bounds.height(),
+            svg_paint("fill", self.background),
+        );
+        for item in self.items() {
+            let _ = match item {
+                Item::Line {
+                    points: [from, to],
+                    color,
+                } => writeln!(
+                    svg,
+                    r#"<line x1="{}" y1="{}" x2="{}" y2="{}" {}/>"#,
+                    from.x,
+                    from.y,
+                    to.x,
+                    to.y,
+                    svg_paint("stroke", *color),
+                ),
+                Item::Curve {
+                    points: [from, a, b, to],
+                    color,
+                } => writeln!(
+                    svg,
+                    r#"<path d="M {} {} C {} {}, {} {}, {} {}" fill="none" {}/>"#,
+                    from.x,
+                    from.y,
+                    a.x,
+                    a.y,
+                    b.x,
+                    b.y,
+                    to.x,
+                    to.y,
+                    svg_paint("stroke", *color),
+                ),
+                Item::Rect { rect, fill, stroke } => writeln!(
+                    svg,
+                    concat!(
+                        r#"<rect x="{}" y="{}" width="{}" height="{}" rx="4""#,
+                        r#" {} {} stroke-width="{}"/>"#
+                    ),
+                    rect.min.x,
+                    rect.min.y,
+                    rect.width(),
+                    rect.height(),
+                    svg_paint("fill", *fill),
+                    svg_paint("stroke", stroke.color),
+                    stroke.width,
+                ),
+                Item::Text { pos, text, color } => writeln!(
+                    svg,
+                    r#"<text x="{}" y="{}" dominant-baseline="hanging" {}>{}</text>"#,
+                    pos.x,
+                    pos.y,
+                    svg_paint("fill", *color),
+                    xml_escaped(text),
+                ),
+            };
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    /// Pixels per layer point of the PNG image, lower than the canvas scale
+    /// if the image would be too large.
+    fn png_scale(&self) -> f32 {
+        let size = self.bounds.size().max(Vec2::splat(1.));
+        self.scale
+            .min(MAX_IMAGE_SIDE / size.x)
+            .min(MAX_IMAGE_SIDE / size.y)
+            .min((MAX_IMAGE_PIXELS / (size.x * size.y)).sqrt())
+    }
+
+    /// Tessellate the scene with egui to rasterize it over the next frames,
+    /// text is taken from egui's font atlas.
+    fn png_job(&self, ctx: &egui::Context, name: String) -> PngJob {
+        let scale = self.png_scale();
+        let to_pixels = TSTransform::new(-self.bounds.min.to_vec2() * scale, scale);
+
+        let shapes: Vec<_> = ctx.fonts(|fonts| {
+            self.items()
+                .map(|item| {
+                    let mut shape = match item {
+                        Item::Line { points, color } => {
+                            Shape::line_segment(*points, Stroke::new(1., *color))
+                        }
+                        Item::Curve { points, color } => CubicBezierShape::from_points_stroke(
+                            *points,
+                            false,
+                            Color32::TRANSPARENT,
+                            Stroke::new(1., *color),
+                        )
+                        .into(),
+                        Item::Rect { rect, fill, stroke } => {
+                            RectShape::new(*rect, Rounding::same(4.), *fill, *stroke).into()
+                        }
+                        Item::Text { pos, text, color } => Shape::galley(
+                            *pos,
+                            fonts.layout_no_wrap(
+                                text.clone(),
+                                FontId::monospace(FONT_SIZE),
+                                *color,
+                            ),
+                            *color,
+                        ),
+                    };
+                    shape.transform(to_pixels);
+                    ClippedShape {
+                        clip_rect: Rect::EVERYTHING,
+                        shape,
+                    }
+                })
+                .collect()
+        });
+        // Taken after the layout since it may add glyphs to the atlas
+        let (font_image, font_image_size) =
+            ctx.fonts(|fonts| (fonts.image(), fonts.font_image_size()));
+        let meshes: Vec<_> = Tessellator::new(
+            1.,
+            TessellationOptions {
+                round_text_to_pixels: false,
+                ..Default::default()
+            },
+            font_image_size,
+            Vec::new(),
+        )
+        .tessellate_shapes(shapes)
+        .into_iter()
+        .filter_map(|primitive| match primitive.primitive {
+            Primitive::Mesh(mesh) => Some(mesh),
+            Primitive::Callback(_) => None,
+        })
+        .collect();
+
+        let size = self.bounds.size() * scale;
+        PngJob {
+            name,
+            raster: Raster::new(
+                (size.x.ceil() as usize).max(1),
+                (size.y.ceil() as usize).max(1),
+                self.background,
+            ),
+            font_image,
+            triangles: meshes.iter().map(|mesh| mesh.indices.len() / 3).sum(),
+            meshes,
+            cursor: (0, 0),
+            done: 0,
+            downscale: (scale < self.scale).then_some(scale / self.scale),
+        }
+    }
+}
+
+/// PNG export in progress, the scene is rasterized a part per frame to keep
+/// the UI responsive.
+struct PngJob {
+    name: String,
+    raster: Raster,
+    font_image: FontImage,
+    meshes: Vec<Mesh>,
+    /// Next mesh and its next triangle to rasterize
+    cursor: (usize, usize),
+    triangles: usize,
+    done: usize,
+    /// Share of the canvas scale if the image had to be made smaller
+    downscale: Option<f32>,
+}
+
+impl PngJob {
+    /// Rasterize triangles until about `budget` pixels are visited, returns
+    /// `true` once the whole scene is rasterized.
+    fn step(&mut self, budget: usize) -> bool {
+        let mut visited = 0;
+        while visited < budget {
+            let (mesh_index, triangle) = self.cursor;
+            let Some(mesh) = self.meshes.get(mesh_index) else {
+                return true;
+            };
+            let Some(indices) = mesh.indices.chunks_exact(3).nth(triangle) else {
+                self.cursor = (mesh_index + 1, 0);
+                continue;
+            };
+            visited += self.raster.fill_triangle(
+                [0, 1, 2].map(|i| &mesh.vertices[indices[i] as usize]),
+                &self.font_image,
+            );
+            self.cursor = (mesh_index, triangle + 1);
+            self.done += 1;
+        }
+        false
+    }
+
+    fn progress(&self) -> f32 {
+        self.done as f32 / self.triangles.max(1) as f32
+    }
+
+    fn finish(self) -> Result<String, ExportError> {
+        let bytes = self.raster.encode()?;
+        let saved = save_file(&format!("{}.png", self.name), "image/png", &bytes)?;
+        Ok(match self.downscale {
+            Some(downscale) => format!(
+                "{saved}, downscaled to {:.0}% to fit {} megapixels",
+                downscale * 100.,
+                MAX_IMAGE_PIXELS / 1e6
+            ),
+            None => saved,
+        })
+    }
+}
+
+/// Image painted with egui meshes, colors have premultiplied alpha until
+/// the image is encoded
+struct Raster {
+    width: usize,
+    height: usize,
+    pixels: Vec<[u8; 4]>,
+}
+
+impl Raster {
+    fn new(width: usize, height: usize, background: Color32) -> Self {
+        Raster {
+            width,
+            height,
+            pixels: vec![background.to_array(); width * height],
+        }
+    }
+
+    /// Returns the number of pixels checked for the triangle.
+    fn fill_triangle(&mut self, vertices: [&Vertex; 3], font_image: &FontImage) -> usize {
+        let edge = |from: Pos2, to: Pos2, p: Pos2| {
+            (to.x - from.x) * (p.y - from.y) - (to.y - from.y) * (p.x - from.x)
+        };
+        let [a, b, c] = vertices.map(|vertex| vertex.pos);
+        let area = edge(a, b, c);
+        if area.abs() < f32::EPSILON {
+            return 0;
+        }
+
+        let min = a.min(b).min(c);
+        let max = a.max(b).max(c);
+        let xs = min.x.floor().clamp(0., self.width as f32) as usize
+            ..max.x.ceil().clamp(0., self.width as f32) as usize;
+        let ys = min.y.floor().clamp(0., self.height as f32) as usize
+            ..max.y.ceil().clamp(0., self.height as f32) as usize;
+        let visited = xs.len() * ys.len();
+        let [font_width, font_height] = font_image.size;
+
+        for y in ys {
+            for x in xs.clone() {
+                let p = Pos2::new(x as f32 + 0.5, y as f32 + 0.5);
+                let weights = [
+                    edge(b, c, p) / area,
+                    edge(c, a, p) / area,
+                    edge(a, b, p) / area,
+                ];
+                if weights.iter().any(|weight| *weight < 0.) {
+                    continue;
+                }
+
+                let mut color = [0.; 4];
+                let mut uv = Vec2::ZERO;
+                for (vertex, weight) in vertices.iter().zip(weights) {
+                    color
+                        .iter_mut()
+                        .zip(vertex.color.to_array())
+                        .for_each(|(channel, value)| *channel += value as f32 / 255. * weight);
+                    uv += vertex.uv.to_vec2() * weight;
+                }
+                let texel_x = ((uv.x * font_width as f32) as usize).min(font_width - 1);
+                let texel_y = ((uv.y * font_height as f32) as usize).min(font_height - 1);
+                let coverage =
+                    font_image.pixels[texel_y * font_width + texel_x].powf(COVERAGE_GAMMA);
+
+                let pixel = &mut self.pixels[y * self.width + x];
+                let alpha = color[3] * coverage;
+                pixel.iter_mut().zip(color).for_each(|(dst, src)| {
+                    let blended = src * coverage + *dst as f32 / 255. * (1. - alpha);
+                    *dst = (blended * 255.).round().clamp(0., 255.) as u8;
+                });
+            }
+        }
+        visited
+    }
+
+    fn encode(mut self) -> Result<Vec<u8>, png::EncodingError> {
+        self.pixels.iter_mut().for_each(|pixel| {
+            let [r, g, b, a] = *pixel;
+            *pixel = Color32::from_rgba_premultiplied(r, g, b, a).to_srgba_unmultiplied();
+        });
+
+        let mut bytes = Vec::new();
+        let mut encoder = png::Encoder::new(&mut bytes, self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder
+            .write_header()?
+            .write_image_data(self.pixels.as_flattened())?;
+        Ok(bytes)
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct ExportWindow {
+    open: bool,
+    format: ImageFormat,
+    scope: ExportScope,
+    /// PNG being rasterized
+    job: Option<PngJob>,
+    /// Outcome of the last export
+    status: Option<Result<String, String>>,
+}
+
+impl ExportWindow {
+    pub(crate) fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+
+    /// `transform` and `canvas` are of the last drawn frame to know which part
+    /// of the canvas is visible.
+    pub(crate) fn show(
+        &mut self,
+        ctx: &egui::Context,
+        tree: &Tree,
+        options: &TreeViewOptions,
+        transform: TSTransform,
+        canvas: Rect,
+    ) {
+        let mut open = self.open;
+        egui::Window::new("Export image")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                egui::ComboBox::from_label("Format")
+                    .selected_text(self.format.as_ref())
+                    .show_ui(ui, |menu| {
+                        ImageFormat::iter().for_each(|format| {
+                            menu.selectable_value(&mut self.format, format, format.as_ref());
+                        });
+                    });
+                egui::ComboBox::from_label("Scope")
+                    .selected_text(self.scope.as_ref())
+                    .show_ui(ui, |menu| {
+                        ExportScope::iter().for_each(|scope| {
+                            menu.selectable_value(&mut self.scope, scope, scope.as_ref());
+                        });
+                    });
+                if self.scope == ExportScope::SelectedSubtree {
+                    ui.label(match tree.selected_node() {
+                        Some((path, key)) => format!("Subtree of {}", path_with_key(&path, &key)),
+                        None => "Select a node of the subtree to export".to_owned(),
+                    });
+                }
+
+                if ui
+                    .add_enabled(self.job.is_none(), egui::Button::new("Export"))
+                    .clicked()
+                {
+                    self.status = self
+                        .export(ctx, tree, options, transform, canvas)
+                        .map_err(|error| error.to_string())
+                        .transpose();
+                }
+                if let Some(job) = &mut self.job {
+                    if job.step(RASTER_PIXELS_PER_FRAME) {
+                        self.status = self
+                            .job
+                            .take()
+                            .map(|job| job.finish().map_err(|error| error.to_string()));
+                    } else {
+                        ui.add(egui::ProgressBar::new(job.progress()).show_percentage());
+                        if let Some(downscale) = job.downscale {
+                            ui.label(format!(
+                                "Downscaled to {:.0}% of the canvas scale",
+                                downscale * 100.
+                            ));
+                        }
+                        ctx.request_repaint();
+                    }
+                }
+                match &self.status {
+                    Some(Ok(message)) => {
+                        ui.colored_label(Color32::GREEN, message);
+                    }
+                    Some(Err(error)) => {
+                        ui.colored_label(Color32::RED, error);
+                    }
+                    None => {}
+                }
+            });
+        self.open = open;
+    }
+
+    /// Save an SVG image right away or start rasterizing a PNG, returns the
+    /// outcome if the export is done.
+    fn export(
+        &mut self,
+        ctx: &egui::Context,
+        tree: &Tree,
+        options: &TreeViewOptions,
+        transform: TSTransform,
+        canvas: Rect,
+    ) -> Result<Option<String>, ExportError> {
+        let (scene, name) = match self.scope {
+            ExportScope::View => (
+                Scene::new(
+                    tree,
+                    options,
+                    |_| true,
+                    transform.inverse() * canvas,
+                    transform.scaling,
+                ),
+                "grovedbg-view".to_owned(),
+            ),
+            ExportScope::SelectedSubtree => {
+                let (path, _) = tree.selected_node().ok_or(ExportError::NoSelection)?;
+                let include = |subtree_path: &Path| subtree_path.starts_with(&path);
+                let bounds = tree
+                    .subtrees
+                    .iter()
+                    .filter(|(subtree_path, subtree)| subtree.visible() && include(subtree_path))
+                    .map(|(_, subtree)| subtree.bounding_rect())
+                    .reduce(|a, b| a.union(b))
+                    .ok_or(ExportError::SubtreeHidden)?;
+                let name = path
+                    .last()
+                    .map(|segment| format!("grovedbg-subtree-{}", bytes_as_hex(segment)))
+                    .unwrap_or_else(|| "grovedbg-root".to_owned());
+                (
+                    Scene::new(tree, options, include, bounds.expand(SUBTREE_MARGIN), 1.),
+                    name,
+                )
+            }
+        };
+
+        match self.format {
+            ImageFormat::Svg => Ok(Some(save_file(
+                &format!("{name}.svg"),
+                "image/svg+xml",
+                scene.to_svg().as_bytes(),
+            )?)),
+            ImageFormat::Png => {
+                self.job = Some(scene.png_job(ctx, name));
+                Ok(None)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::DisplayVariant;
+
+    #[test]
+    fn svg_of_collapsed_subtree() {
+        let mut tree = Tree::new();
+        tree.set_root(b"a<b".to_vec());
+        tree.insert(Path::default(), b"a<b".to_vec(), Node::new_sum_item(7));
+        tree.insert(Path::default(), b"n\0l".to_vec(), Node::new_sum_item(8));
+        for key in [b"a<b", b"n\0l"] {
+            tree.get_node(&Path::default(), key)
+                .unwrap()
+                .ui_state
+                .borrow_mut()
+                .key_display_variant = DisplayVariant::String;
+        }
+
+        let bounds = Rect::from_min_size(Pos2::new(-100., 0.), Vec2::new(200., 100.));
+        let svg = Scene::new(&tree, &Default::default(), |_| true, bounds, 2.).to_svg();
+
+        assert!(svg.starts_with(r#"<svg xmlns="http://www.w3.org/2000/svg" width="400""#));
+        assert!(svg.contains(">a&lt;b</text>"));
+        assert!(svg.contains(">n\u{fffd}l</text>") && !svg.contains('\0'));
+        assert!(svg.contains(">  Value: 7</text>"));
+        assert!(svg.trim_end().ends_with("</svg>"));
+    }
+
+    #[test]
+    fn png_size_is_capped() {
+        let bounds = Rect::from_min_size(Pos2::ZERO, Vec2::new(20000., 10000.));
+        let scale = Scene::new(&Tree::new(), &Default::default(), |_| true, bounds, 1.).png_scale();
+        let size = bounds.size() * scale;
+        assert!(size.x * size.y <= MAX_IMAGE_PIXELS && size.x <= MAX_IMAGE_SIDE);
+
+        let mut raster = Raster::new(2, 1, Color32::from_rgba_unmultiplied(200, 100, 50, 128));
+        raster.pixels[1] = Color32::WHITE.to_array();
+        let bytes = raster.encode().unwrap();
+        let mut reader = png::Decoder::new(bytes.as_slice()).read_info().unwrap();
+        let mut data = vec![0; reader.output_buffer_size()];
+        reader.next_frame(&mut data).unwrap();
+        assert!(data[..4]
+            .iter()
+            .zip([200, 100, 50, 128])
+            .all(|(decoded, expected)| decoded.abs_diff(expected) <= 2));
+        assert_eq!(data[4..8], [255; 4]);
+    }
+}
@@ -0,0 +1,311 @@
+//! Classic hex dump view of binary data with byte range selection and search
+
+use std::fmt::Write;
+
+use eframe::{
+    egui::{self, Label, RichText, Sense, TextStyle},
+    epaint::Color32,
+};
+
+const BYTES_PER_ROW: usize = 16;
+const DUMP_HEIGHT: f32 = 300.;
+const SELECTION_COLOR: Color32 = Color32::from_rgb(60, 60, 120);
+const MATCH_COLOR: Color32 = Color32::from_rgb(90, 70, 20);
+
+/// Hex dump UI state of an item, kept per node
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct HexDumpState {
+    /// Inclusive range of selected bytes
+    selection: Option<(usize, usize)>,
+    search: String,
+    search_error: bool,
+    matches: Vec<usize>,
+    match_len: usize,
+    current_match: usize,
+    /// Byte offset to scroll to on the next frame
+    jump_to: Option<usize>,
+}
+
+impl HexDumpState {
+    fn select(&mut self, offset: usize, extend: bool) {
+        self.selection = match (self.selection, extend) {
+            (Some((start, _)), true) => Some((start.min(offset), start.max(offset))),
+            _ => Some((offset, offset)),
+        };
+    }
+
+    fn is_selected(&self, offset: usize) -> bool {
+        self.selection
+            .map(|(start, end)| (start..=end).contains(&offset))
+            .unwrap_or_default()
+    }
+
+    fn search(&mut self, bytes: &[u8]) {
+        self.matches.clear();
+        self.current_match = 0;
+        match parse_hex_pattern(&self.search) {
+            Some(pattern) => {
+                self.search_error = false;
+                self.matches = find_all(bytes, &pattern);
+                self.match_len = pattern.len();
+                self.jump_to_match();
+            }
+            None => self.search_error = true,
+        }
+    }
+
+    fn jump_to_match(&mut self) {
+        if let Some(offset) = self.matches.get(self.current_match).copied() {
+            self.jump_to = Some(offset);
+            self.selection = Some((offset, offset + self.match_len.saturating_sub(1)));
+        }
+    }
+}
+
+/// Parse a hex pattern ignoring whitespace, `None` if it's not valid hex.
+fn parse_hex_pattern(pattern: &str) -> Option<Vec<u8>> {
+    let compact: String = pattern.chars().filter(|c| !c.is_whitespace()).collect();
+    let compact = compact.strip_prefix("0x").unwrap_or(&compact);
+    if compact.is_empty() {
+        return None;
+    }
+    hex::decode(compact).ok()
+}
+
+/// Offsets of all, possibly overlapping, occurrences of the pattern.
+fn find_all(haystack: &[u8], pattern: &[u8]) -> Vec<usize> {
+    if pattern.is_empty() || pattern.len() > haystack.len() {
+        return Vec::new();
+    }
+    haystack
+        .windows(pattern.len())
+        .enumerate()
+        .filter_map(|(offset, window)| (window == pattern).then_some(offset))
+        .collect()
+}
+
+fn ascii_column(row: &[u8]) -> String {
+    row.iter()
+        .map(|b| {
+            if b.is_ascii_graphic() || *b == b' ' {
+                *b as char
+            } else {
+                '.'
+            }
+        })
+        .collect()
+}
+
+/// Interpretations of a selected byte range.
+fn decode_selection(bytes: &[u8]) -> Vec<(&'static str, String)> {
+    let mut decoded = Vec::new();
+    macro_rules! integers {
+        ($($len:literal => $u:ty, $i:ty;)*) => {
+            match bytes.len() {
+                $(
+                    $len => {
+                        let array: [u8; $len] = bytes.try_into().expect("length checked");
+                        decoded.push(("Unsigned BE", <$u>::from_be_bytes(array).to_string()));
+                        decoded.push(("Unsigned LE", <$u>::from_le_bytes(array).to_string()));
+                        decoded.push(("Signed BE", <$i>::from_be_bytes(array).to_string()));
+                        decoded.push(("Signed LE", <$i>::from_le_bytes(array).to_string()));
+                    }
+                )*
+                _ => {}
+            }
+        };
+    }
+    integers! {
+        1 => u8, i8;
+        2 => u16, i16;
+        4 => u32, i32;
+        8 => u64, i64;
+        16 => u128, i128;
+    }
+    if let Some(varint) = decode_varint(bytes) {
+        decoded.push(("Varint", varint.to_string()));
+    }
+    decoded.push(("UTF-8", String::from_utf8_lossy(bytes).into_owned()));
+    decoded
+}
+
+/// Decode unsigned LEB128 varint that takes exactly all the bytes.
+fn decode_varint(bytes: &[u8]) -> Option<u64> {
+    if bytes.is_empty() || bytes.len() > 10 {
+        return None;
+    }
+    let (last, init) = bytes.split_last()?;
+    if last & 0x80 != 0 || init.iter().any(|b| b & 0x80 == 0) {
+        return None;
+    }
+    bytes.iter().enumerate().try_fold(0u64, |acc, (i, b)| {
+        ((*b & 0x7f) as u64)
+            .checked_shl(7 * i as u32)
+            .map(|part| acc | part)
+    })
+}
+
+pub(crate) fn hex_dump(
+    ui: &mut egui::Ui,
+    id_source: egui::Id,
+    bytes: &[u8],
+    state: &mut HexDumpState,
+) {
+    ui.horizontal(|search_line| {
+        let response = search_line.add(
+            egui::TextEdit::singleline(&mut state.search)
+                .hint_text("Hex pattern")
+                .desired_width(150.),
+        );
+        if search_line.button("Find").clicked()
+            || (response.lost_focus() && search_line.input(|i| i.key_pressed(egui::Key::Enter)))
+        {
+            state.search(bytes);
+        }
+        if search_line
+            .add_enabled(state.matches.len() > 1, egui::Button::new("⬅"))
+            .clicked()
+        {
+            state.current_match =
+                (state.current_match + state.matches.len() - 1) % state.matches.len();
+            state.jump_to_match();
+        }
+        if search_line
+            .add_enabled(state.matches.len() > 1, egui::Button::new("➡"))
+            .clicked()
+        {
+            state.current_match = (state.current_match + 1) % state.matches.len();
+            state.jump_to_match();
+        }
+        if state.search_error {
+            search_line.colored_label(Color32::RED, "Invalid hex pattern");
+        } else if !state.matches.is_empty() {
+            search_line.label(format!(
+                "{}/{} at {:#x}",
+                state.current_match + 1,
+                state.matches.len(),
+                state.matches[state.current_match]
+            ));
+        }
+    });
+
+    let row_height = ui.text_style_height(&TextStyle::Monospace);
+    let n_rows = bytes.len().div_ceil(BYTES_PER_ROW);
+    let mut scroll_area = egui::ScrollArea::both()
+        .id_source(id_source.with("hex_dump"))
+        .max_height(DUMP_HEIGHT)
+        .auto_shrink([false, true]);
+    if let Some(offset) = state.jump_to.take() {
+        scroll_area = scroll_area.vertical_scroll_offset(
+            (offset / BYTES_PER_ROW) as f32 * (row_height + ui.spacing().item_spacing.y),
+        );
+    }
+
+    let is_match = |offset: usize| {
+        state
+            .matches
+            .iter()
+            .any(|m| (*m..*m + state.match_len).contains(&offset))
+    };
+
+    let mut clicked = None;
+    scroll_area.show_rows(ui, row_height, n_rows, |ui, rows| {
+        ui.spacing_mut().item_spacing.x = 4.;
+        for row in rows {
+            let row_offset = row * BYTES_PER_ROW;
+            let row_bytes = &bytes[row_offset..(row_offset + BYTES_PER_ROW).min(bytes.len())];
+            ui.horizontal(|line| {
+                line.label(
+                    RichText::new(format!("{row_offset:08x}"))
+                        .monospace()
+                        .color(Color32::GRAY),
+                );
+                for (i, byte) in row_bytes.iter().enumerate() {
+                    let offset = row_offset + i;
+                    let mut text = RichText::new(format!("{byte:02x}")).monospace();
+                    if state.is_selected(offset) {
+                        text = text.background_color(SELECTION_COLOR);
+                    } else if is_match(offset) {
+                        text = text.background_color(MATCH_COLOR);
+                    }
+                    if line.add(Label::new(text).sense(Sense::click())).clicked() {
+                        clicked = Some(offset);
+                    }
+                }
+                // Keep the ASCII column aligned on the last row
+                let mut padding = String::new();
+                (row_bytes.len()..BYTES_PER_ROW).for_each(|_| {
+                    let _ = write!(padding, "   ");
+                });
+                line.label(
+                    RichText::new(format!("{padding}{}", ascii_column(row_bytes)))
+                        .monospace()
+                        .color(Color32::LIGHT_GRAY),
+                );
+            });
+        }
+    });
+
+    if let Some(offset) = clicked {
+        let extend = ui.input(|i| i.modifiers.shift);
+        state.select(offset, extend);
+    }
+
+    if let Some((start, end)) = state.selection {
+        ui.label(format!(
+            "Selected {:#x}..={:#x} ({} bytes), shift+click to extend",
+            start,
+            end,
+            end - start + 1
+        ));
+        egui::Grid::new(id_source.with("hex_dump_selection"))
+            .striped(true)
+            .show(ui, |grid| {
+                for (name, value) in decode_selection(&bytes[start..=end.min(bytes.len() - 1)]) {
+                    grid.label(RichText::new(name).color(Color32::GRAY));
+                    grid.add(Label::new(RichText::new(value).monospace()).wrap(true));
+                    grid.end_row();
+                }
+            });
+    } else {
+        ui.label("Click a byte to select it, shift+click to select a range");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex_pattern_parsing() {
+        assert_eq!(
+            parse_hex_pattern("de ad BE ef"),
+            Some(vec![0xde, 0xad, 0xbe, 0xef])
+        );
+        assert_eq!(parse_hex_pattern("0x0102"), Some(vec![1, 2]));
+        assert_eq!(parse_hex_pattern("abc"), None);
+        assert_eq!(parse_hex_pattern(""), None);
+    }
+
+    #[test]
+    fn pattern_search() {
+        assert_eq!(find_all(&[1, 1, 1, 2], &[1, 1]), vec![0, 1]);
+        assert_eq!(find_all(&[1, 2], &[3]), Vec::<usize>::new());
+        assert_eq!(find_all(&[1], &[1, 2]), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn selection_decoding() {
+        let decoded = decode_selection(&[0x01, 0x00]);
+        assert!(decoded.contains(&("Unsigned BE", "256".to_owned())));
+        assert!(decoded.contains(&("Unsigned LE", "1".to_owned())));
+
+        assert_eq!(decode_varint(&[0xac, 0x02]), Some(300));
+        assert_eq!(decode_varint(&[0xac]), None);
+    }
+
+    #[test]
+    fn ascii_column_replaces_non_printable() {
+        assert_eq!(ascii_column(b"ab\x00\xff c"), "ab.. c");
+    }
+}
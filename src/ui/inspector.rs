@@ -0,0 +1,338 @@
+//! Node inspector panel to show complete data of a selected node
+
+use eframe::{
+    egui::{self, Label, RichText},
+    epaint::Color32,
+};
+
+use super::{
+    common::{bytes_as_base64, bytes_as_rust_literal, path_with_key},
+    contracts::document_view,
+    hex_dump::hex_dump,
+    theme::Theme,
+};
+use crate::model::{contract::Contracts, hash::CryptoHash, Element, FeatureType, Tree};
+
+const INSPECTOR_WIDTH: f32 = 450.;
+/// Items larger than this are shown as hex dump by default
+const HEX_DUMP_OPEN_THRESHOLD: usize = 64;
+
+pub(crate) fn draw_inspector(
+    ctx: &egui::Context,
+    tree: &Tree,
+    contracts: &Contracts,
+    theme: &Theme,
+) {
+    let Some((path, key)) = tree.selected_node() else {
+        return;
+    };
+
+    egui::SidePanel::right("inspector")
+        .resizable(true)
+        .default_width(INSPECTOR_WIDTH)
+        .show(ctx, |ui| {
+            ui.horizontal(|header| {
+                header.heading("Inspector");
+                if header.button("❌").clicked() {
+                    tree.clear_selection();
+                }
+            });
+            ui.separator();
+
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                egui::CollapsingHeader::new(format!("Path ({} segments)", path.len()))
+                    .default_open(true)
+                    .show(ui, |ui| {
+                        if path.is_empty() {
+                            ui.label("Root subtree");
+                        }
+                        for (i, segment) in path.iter().enumerate() {
+                            bytes_field(ui, &format!("Segment {i}"), segment);
+                        }
+                    });
+                if let Some(documents_subtree) = contracts.documents_subtree(&path) {
+                    ui.label(documents_subtree.label());
+                }
+                ui.separator();
+
+                bytes_field(ui, "Key", &key);
+                if let Some(value) = contracts
+                    .documents_subtree(&path)
+                    .and_then(|subtree| subtree.decode_key(&key))
+                {
+                    decoded_line(ui, "Contract", &value.to_string());
+                }
+                ui.separator();
+
+                let Some(node) = tree.get_node(&path, &key) else {
+                    ui.label("Node is not loaded");
+                    return;
+                };
+
+                ui.label(
+                    RichText::new(node.element.as_ref())
+                        .strong()
+                        .color(theme.element_color(&node.element)),
+                );
+                match &node.element {
+                    Element::Item { value } => {
+                        bytes_field(ui, "Value", value);
+                        egui::CollapsingHeader::new("Hex dump")
+                            .default_open(value.len() > HEX_DUMP_OPEN_THRESHOLD)
+                            .show(ui, |ui| {
+                                hex_dump(
+                                    ui,
+                                    egui::Id::new(("inspector", &path, &key)),
+                                    value,
+                                    &mut node.ui_state.borrow_mut().hex_dump,
+                                );
+                            });
+                        if let Some(document) = contracts.decode_item(&path, value) {
+                            document_view(
+                                ui,
+                                theme,
+                                egui::Id::new(("inspector", &path, &key)),
+                                document,
+                            );
+                        }
+                    }
+                    Element::SumItem { value } => {
+                        decoded_line(ui, "Value", &value.to_string());
+                    }
+                    Element::Reference {
+                        path: ref_path,
+                        key: ref_key,
+                    } => {
+                        for (i, segment) in ref_path.iter().enumerate() {
+                            bytes_field(ui, &format!("Target path segment {i}"), segment);
+                        }
+                        bytes_field(ui, "Target key", ref_key);
+                        match tree.reference_status(&path, &key) {
+                            Some(status) if status.is_broken() => {
+                                ui.colored_label(Color32::RED, format!("⚠ {status}"));
+                            }
+                            Some(status) => decoded_line(ui, "Target", &status.to_string()),
+                            None => decoded_line(ui, "Target", "not resolved yet"),
+                        }
+                        if tree.get_node(ref_path, ref_key).is_some()
+                            && ui.button("Inspect target").clicked()
+                        {
+                            tree.select_node(ref_path.clone(), ref_key.clone());
+                        }
+                    }
+                    Element::Sumtree { root_key, sum } => {
+                        decoded_line(ui, "Sum", &sum.to_string());
+                        root_key_field(ui, root_key.as_deref());
+                        match tree.verify_sum(&path, &key) {
+                            Some(verification) => {
+                                decoded_line(
+                                    ui,
+                                    "Computed sum",
+                                    &verification.computed.to_string(),
+                                );
+                                if !verification.is_consistent() {
+                                    ui.colored_label(
+                                        Color32::RED,
+                                        "⚠ Stored sum doesn't match child subtree contents",
+                                    );
+                                }
+                                if verification.overflows() {
+                                    ui.colored_label(Color32::RED, "⚠ Computed sum overflows i64");
+                                } else if verification.overflow_risk() {
+                                    ui.colored_label(
+                                        Color32::from_rgb(255, 165, 0),
+                                        "⚠ Sum is close to i64 bounds",
+                                    );
+                                }
+                            }
+                            None => decoded_line(
+                                ui,
+                                "Computed sum",
+                                "unknown, child subtree is not fully loaded",
+                            ),
+                        }
+                    }
+                    Element::Subtree { root_key } => {
+                        root_key_field(ui, root_key.as_deref());
+                    }
+                    Element::SubtreePlaceholder => {
+                        ui.label("Subtree element is not fetched yet");
+                    }
+                }
+                ui.separator();
+
+                let referrers = tree.referrers(&path, &key);
+                if referrers.is_empty() {
+                    ui.label(RichText::new("No loaded references point here").color(Color32::GRAY));
+                } else {
+                    ui.label(
+                        RichText::new(format!("Referenced by ({})", referrers.len())).strong(),
+                    );
+                    for (ref_path, ref_key) in referrers {
+                        ui.horizontal(|line| {
+                            if line.small_button("Inspect").clicked() {
+                                tree.reveal_subtree(&ref_path);
+                                tree.select_node(ref_path.clone(), ref_key.clone());
+                            }
+                            line.label(path_with_key(&ref_path, &ref_key));
+                        });
+                    }
+                }
+                ui.separator();
+
+                if let Some(flags) = &node.element_flags {
+                    bytes_field(ui, "Element flags", flags);
+                } else {
+                    ui.label(RichText::new("No element flags").color(Color32::GRAY));
+                }
+                match node.feature_type {
+                    Some(FeatureType::Basic) => decoded_line(ui, "Feature type", "Basic"),
+                    Some(FeatureType::Summed(sum)) => {
+                        decoded_line(ui, "Feature type", &format!("Summed, contributes {sum}"))
+                    }
+                    None => decoded_line(ui, "Feature type", "unknown"),
+                }
+                ui.separator();
+
+                match tree
+                    .subtrees
+                    .get(&path)
+                    .and_then(|subtree| subtree.node_heights(&key))
+                {
+                    Some(heights) => {
+                        decoded_line(ui, "Height", &heights.height().to_string());
+                        decoded_line(
+                            ui,
+                            "Left / right subtree heights",
+                            &format!("{} / {}", heights.left, heights.right),
+                        );
+                        let balance = format!("{:+}", heights.balance_factor());
+                        if heights.is_balanced() {
+                            decoded_line(ui, "Balance factor", &balance);
+                        } else {
+                            ui.colored_label(
+                                Color32::RED,
+                                format!("⚠ Balance factor {balance} violates AVL invariant"),
+                            );
+                        }
+                    }
+                    None => decoded_line(ui, "Height", "unknown, branch is not fully loaded"),
+                }
+                ui.separator();
+
+                ui.label(RichText::new("Hashes").strong());
+                hash_field(ui, "Value hash", node.value_hash.as_ref());
+                hash_field(ui, "KV hash", node.kv_digest_hash.as_ref());
+                hash_field(ui, "Node hash", node.node_hash().as_ref());
+                hash_field(ui, "Left child hash", node.left_merk_hash.as_ref());
+                hash_field(ui, "Right child hash", node.right_merk_hash.as_ref());
+                if let Element::Subtree { .. } | Element::Sumtree { .. } = &node.element {
+                    let mut child_path = path.clone();
+                    child_path.push(key.clone());
+                    let root_hash = tree.subtrees.get(&child_path).and_then(|s| s.root_hash());
+                    hash_field(ui, "Child subtree root hash", root_hash.as_ref());
+                    ui.label(
+                        RichText::new(
+                            "Value hash of a subtree element combines the hash of its serialized \
+                             value with the child subtree root hash",
+                        )
+                        .small()
+                        .color(Color32::GRAY),
+                    );
+                }
+                for mismatch in
+                    node.verify_hashes(tree.subtrees.get(&path).expect("node exists"), &key)
+                {
+                    ui.colored_label(Color32::RED, format!("⚠ {mismatch}"));
+                }
+                ui.separator();
+
+                for (title, child) in [
+                    ("Left child", &node.left_child),
+                    ("Right child", &node.right_child),
+                ] {
+                    if let Some(child) = child {
+                        bytes_field(ui, title, child);
+                        if tree.get_node(&path, child).is_some()
+                            && ui
+                                .button(format!("Inspect {}", title.to_lowercase()))
+                                .clicked()
+                        {
+                            tree.select_node(path.clone(), child.clone());
+                        }
+                    } else {
+                        ui.label(RichText::new(format!("{title}: none")).color(Color32::GRAY));
+                    }
+                }
+            });
+        });
+}
+
+fn root_key_field(ui: &mut egui::Ui, root_key: Option<&[u8]>) {
+    if let Some(root_key) = root_key {
+        bytes_field(ui, "Root key", root_key);
+    } else {
+        ui.label("Empty subtree");
+    }
+}
+
+fn hash_field(ui: &mut egui::Ui, title: &str, hash: Option<&CryptoHash>) {
+    ui.horizontal(|line| {
+        line.label(RichText::new(title).color(Color32::GRAY));
+        if let Some(hash) = hash {
+            line.label(RichText::new(hex::encode(hash)).monospace());
+            if line
+                .small_button("📋")
+                .on_hover_text("Copy as hex")
+                .clicked()
+            {
+                line.ctx().copy_text(hex::encode(hash));
+            }
+        } else {
+            line.label("unknown");
+        }
+    });
+}
+
+fn decoded_line(ui: &mut egui::Ui, title: &str, text: &str) {
+    ui.label(RichText::new(title).small().color(Color32::GRAY));
+    ui.add(Label::new(RichText::new(text).monospace()).wrap(true));
+}
+
+/// Show binary data in all representations at full length with buttons to
+/// copy it.
+fn bytes_field(ui: &mut egui::Ui, title: &str, bytes: &[u8]) {
+    ui.horizontal(|line| {
+        line.label(RichText::new(title).strong());
+        line.label(RichText::new(format!("{} bytes", bytes.len())).color(Color32::GRAY));
+        copy_buttons(line, bytes);
+    });
+    decoded_line(ui, "Hex", &hex::encode(bytes));
+    decoded_line(ui, "UTF-8", &String::from_utf8_lossy(bytes));
+    decoded_line(ui, "Integers", &format!("{bytes:?}"));
+    decoded_line(ui, "Base64", &bytes_as_base64(bytes));
+}
+
+fn copy_buttons(ui: &mut egui::Ui, bytes: &[u8]) {
+    if ui
+        .small_button("Hex")
+        .on_hover_text("Copy as hex")
+        .clicked()
+    {
+        ui.ctx().copy_text(hex::encode(bytes));
+    }
+    if ui
+        .small_button("Base64")
+        .on_hover_text("Copy as Base64")
+        .clicked()
+    {
+        ui.ctx().copy_text(bytes_as_base64(bytes));
+    }
+    if ui
+        .small_button("Rust")
+        .on_hover_text("Copy as Rust byte string literal")
+        .clicked()
+    {
+        ui.ctx().copy_text(bytes_as_rust_literal(bytes));
+    }
+}
@@ -0,0 +1,117 @@
+//! Window with Merk structural integrity violations and sum mismatches of the
+//! loaded data
+
+use eframe::{
+    egui::{self, RichText},
+    epaint::Color32,
+};
+
+use super::{
+    common::{binary_label, path_label},
+    DisplayVariant,
+};
+use crate::model::{integrity::Issue, sum::SumVerification, Key, Path, Tree};
+
+#[derive(Default)]
+pub(crate) struct IntegrityWindow {
+    open: bool,
+    /// Issues found by the last check, `None` if it wasn't run yet
+    issues: Option<Vec<Issue>>,
+    /// Sumtrees with sums not matching their loaded contents or close to
+    /// overflow, found by the last check
+    sum_issues: Vec<(Path, Key, SumVerification)>,
+    display_variant: DisplayVariant,
+}
+
+impl IntegrityWindow {
+    pub(crate) fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+
+    pub(crate) fn show(&mut self, ctx: &egui::Context, tree: &Tree) {
+        let mut open = self.open;
+        egui::Window::new("Integrity check")
+            .open(&mut open)
+            .default_width(500.)
+            .show(ctx, |ui| {
+                ui.horizontal(|line| {
+                    if line.button("Check").clicked() {
+                        self.issues = Some(tree.check_integrity());
+                        self.sum_issues = tree.sum_issues();
+                    }
+                    match &self.issues {
+                        Some(issues) if issues.is_empty() && self.sum_issues.is_empty() => {
+                            line.colored_label(Color32::GREEN, "✔ No violations found");
+                        }
+                        Some(issues) => {
+                            line.colored_label(
+                                Color32::RED,
+                                format!(
+                                    "{} violations, {} sum issues found",
+                                    issues.len(),
+                                    self.sum_issues.len()
+                                ),
+                            );
+                        }
+                        None => {
+                            line.label("Walk loaded subtrees looking for violations");
+                        }
+                    }
+                });
+                ui.separator();
+
+                let Some(issues) = &self.issues else {
+                    return;
+                };
+                egui::ScrollArea::both().max_height(400.).show(ui, |ui| {
+                    egui::Grid::new("integrity_issues")
+                        .striped(true)
+                        .show(ui, |grid| {
+                            for issue in issues.iter() {
+                                path_label(grid, &issue.path, &mut self.display_variant);
+                                binary_label(grid, &issue.key, &mut self.display_variant);
+                                grid.label(
+                                    RichText::new(issue.violation.to_string()).color(Color32::RED),
+                                );
+                                if tree.get_node(&issue.path, &issue.key).is_some()
+                                    && grid.small_button("Inspect").clicked()
+                                {
+                                    tree.reveal_subtree(&issue.path);
+                                    tree.select_node(issue.path.clone(), issue.key.clone());
+                                }
+                                grid.end_row();
+                            }
+                            for (path, key, verification) in self.sum_issues.iter() {
+                                path_label(grid, path, &mut self.display_variant);
+                                binary_label(grid, key, &mut self.display_variant);
+                                grid.label(
+                                    RichText::new(sum_issue_description(verification))
+                                        .color(Color32::RED),
+                                );
+                                if grid.small_button("Inspect").clicked() {
+                                    tree.reveal_subtree(path);
+                                    tree.select_node(path.clone(), key.clone());
+                                }
+                                grid.end_row();
+                            }
+                        });
+                });
+            });
+        self.open = open;
+    }
+}
+
+fn sum_issue_description(verification: &SumVerification) -> String {
+    let mut description = if verification.is_consistent() {
+        format!("Sum {} is close to i64 bounds", verification.stored)
+    } else {
+        format!(
+            "Stored sum {} doesn't match computed {}",
+            verification.stored, verification.computed
+        )
+    };
+    if verification.overflows() {
+        description.push_str(", computed sum overflows i64");
+    }
+    description
+}
@@ -0,0 +1,82 @@
+//! Key bindings to move the selection and act on the selected node
+
+use eframe::egui::{self, Key};
+use tokio::sync::mpsc::Sender;
+
+use crate::{
+    fetch::Message,
+    model::{navigation::Step, Element, Tree},
+};
+
+const NAVIGATION_KEYS: [(Key, Step); 5] = [
+    (Key::ArrowUp, Step::Parent),
+    (Key::ArrowLeft, Step::Left),
+    (Key::ArrowRight, Step::Right),
+    (Key::PageUp, Step::ParentSubtree),
+    (Key::PageDown, Step::ChildSubtree),
+];
+
+pub(crate) const KEY_BINDINGS_HINT: &str = concat!(
+    "↑ parent node, ←/→ left/right child\n",
+    "PageUp/PageDown parent/child subtree\n",
+    "Enter expand/collapse subtree\n",
+    "Space show/hide child subtree\n",
+    "Delete unload child subtree",
+);
+
+/// Handle key presses unless a text input has focus, returns true if the
+/// selection has moved.
+pub(crate) fn handle_keyboard(ctx: &egui::Context, tree: &Tree, sender: &Sender<Message>) -> bool {
+    if ctx.wants_keyboard_input() {
+        return false;
+    }
+
+    let mut moved = false;
+    for (key, step) in NAVIGATION_KEYS {
+        if !ctx.input(|i| i.key_pressed(key)) {
+            continue;
+        }
+        if let Some((path, key)) = tree.navigate(step) {
+            moved = true;
+            if tree.get_node(&path, &key).is_none() {
+                // TODO error handling
+                let _ = sender.blocking_send(Message::FetchNode { path, key });
+            }
+        }
+    }
+
+    let Some((path, key)) = tree.selected_node() else {
+        return moved;
+    };
+    let Some(subtree_ctx) = tree.get_subtree(&path) else {
+        return moved;
+    };
+
+    if ctx.input(|i| i.key_pressed(Key::Enter)) {
+        let subtree = subtree_ctx.subtree();
+        if subtree.is_expanded() {
+            subtree.set_collapsed();
+        } else if subtree.root_node().is_some() {
+            subtree.set_expanded();
+            subtree_ctx.set_children_invisible();
+        }
+    }
+
+    let is_subtree = tree.get_node(&path, &key).is_some_and(|node| {
+        matches!(
+            node.element,
+            Element::Subtree { .. } | Element::Sumtree { .. } | Element::SubtreePlaceholder
+        )
+    });
+    if is_subtree && ctx.input(|i| i.key_pressed(Key::Space)) {
+        subtree_ctx.set_child_visibility(&key, !subtree_ctx.is_child_visible(&key));
+    }
+    if is_subtree && ctx.input(|i| i.key_pressed(Key::Delete)) {
+        let mut child_path = path.clone();
+        child_path.push(key.clone());
+        // TODO error handling
+        let _ = sender.blocking_send(Message::UnloadSubtree { path: child_path });
+    }
+
+    moved
+}
@@ -0,0 +1,204 @@
+//! Legend of element colors to filter and highlight elements by their types
+
+use std::{
+    collections::{HashMap, HashSet},
+    mem::{self, Discriminant},
+};
+
+use eframe::egui::{self, RichText};
+use strum::IntoEnumIterator;
+
+use super::theme::Theme;
+use crate::model::{Element, Key, Path, Tree};
+
+/// Opacity of elements which types are not highlighted
+const DIMMED_OPACITY: f32 = 0.25;
+
+/// Element types shown on the canvas, toggled from the legend
+#[derive(Debug, Default)]
+pub(crate) struct ElementTypesFilter {
+    hidden: HashSet<Discriminant<Element>>,
+    highlighted: Option<Discriminant<Element>>,
+}
+
+impl ElementTypesFilter {
+    pub(crate) fn is_visible(&self, element: &Element) -> bool {
+        !self.hidden.contains(&mem::discriminant(element))
+    }
+
+    fn set_visible(&mut self, element: &Element, visible: bool) {
+        if visible {
+            self.hidden.remove(&mem::discriminant(element));
+        } else {
+            self.hidden.insert(mem::discriminant(element));
+        }
+    }
+
+    fn is_highlighted(&self, element: &Element) -> bool {
+        self.highlighted == Some(mem::discriminant(element))
+    }
+
+    /// Elements of other types are dimmed if one is highlighted
+    pub(crate) fn opacity(&self, element: &Element) -> f32 {
+        match self.highlighted {
+            Some(highlighted) if highlighted != mem::discriminant(element) => DIMMED_OPACITY,
+            _ => 1.0,
+        }
+    }
+}
+
+/// Loaded element of the type next to or previous to the selected node, with
+/// wrapping around.
+fn jump_target(tree: &Tree, element: &Element, forward: bool) -> Option<(Path, Key)> {
+    let kind = mem::discriminant(element);
+    let mut elements = tree.subtrees.iter().flat_map(|(path, subtree)| {
+        subtree
+            .nodes
+            .iter()
+            .filter(move |(_, node)| mem::discriminant(&node.element) == kind)
+            .map(move |(key, _)| (path, key))
+    });
+    let selected = tree.selected_node();
+    let selected = selected.as_ref().map(|(path, key)| (path, key));
+
+    let target = if forward {
+        let mut first = None;
+        elements
+            .find(|target| {
+                first.get_or_insert(*target);
+                selected.is_none_or(|selected| *target > selected)
+            })
+            .or(first)
+    } else {
+        let mut last = None;
+        let mut previous = None;
+        for target in elements {
+            if selected.is_some_and(|selected| target < selected) {
+                previous = Some(target);
+            }
+            last = Some(target);
+        }
+        previous.or(last)
+    };
+    target.map(|(path, key)| (path.clone(), key.clone()))
+}
+
+/// Draw element types with their colors and counts of loaded elements, types
+/// can be hidden or highlighted. Returns `true` if the selection jumped to
+/// another element.
+pub(crate) fn draw_legend(
+    ui: &mut egui::Ui,
+    tree: &Tree,
+    theme: &Theme,
+    filter: &mut ElementTypesFilter,
+) -> bool {
+    let mut counts: HashMap<Discriminant<Element>, usize> = HashMap::new();
+    tree.subtrees
+        .values()
+        .flat_map(|subtree| subtree.nodes.values())
+        .for_each(|node| *counts.entry(mem::discriminant(&node.element)).or_default() += 1);
+
+    let mut jump = None;
+    egui::Area::new(egui::Id::new("legend"))
+        .anchor(egui::Align2::RIGHT_TOP, [-20.0, 50.0])
+        .order(egui::Order::Foreground)
+        .show(ui.ctx(), |ui| {
+            egui::Frame::default()
+                .rounding(egui::Rounding::same(4.0))
+                .inner_margin(egui::Margin::same(8.0))
+                .stroke(ui.ctx().style().visuals.window_stroke)
+                .fill(ui.style().visuals.panel_fill)
+                .show(ui, |ui| {
+                    ui.style_mut().wrap = Some(false);
+                    Element::iter().for_each(|element| {
+                        let count = counts
+                            .get(&mem::discriminant(&element))
+                            .copied()
+                            .unwrap_or_default();
+                        ui.horizontal(|line| {
+                            let mut visible = filter.is_visible(&element);
+                            if line
+                                .checkbox(&mut visible, "")
+                                .on_hover_text("Show on the canvas")
+                                .changed()
+                            {
+                                filter.set_visible(&element, visible);
+                            }
+
+                            let highlighted = filter.is_highlighted(&element);
+                            if line
+                                .selectable_label(
+                                    highlighted,
+                                    RichText::new(format!("{} ({count})", element.as_ref()))
+                                        .color(theme.element_color(&element)),
+                                )
+                                .on_hover_text("Highlight")
+                                .clicked()
+                            {
+                                filter.highlighted =
+                                    (!highlighted).then(|| mem::discriminant(&element));
+                            }
+
+                            if line
+                                .add_enabled(count > 0, egui::Button::new("⏴").small())
+                                .on_hover_text("Previous")
+                                .clicked()
+                            {
+                                jump = jump_target(tree, &element, false);
+                            }
+                            if line
+                                .add_enabled(count > 0, egui::Button::new("⏵").small())
+                                .on_hover_text("Next")
+                                .clicked()
+                            {
+                                jump = jump_target(tree, &element, true);
+                            }
+                        });
+                    });
+                });
+        });
+
+    if let Some((path, key)) = jump {
+        tree.reveal_node(&path, &key);
+        tree.select_node(path, key);
+        true
+    } else {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Node;
+
+    #[test]
+    fn jumps_wrap_around() {
+        let mut tree = Tree::new();
+        tree.set_root(b"b".to_vec());
+        for key in [b"a", b"b", b"c"] {
+            tree.insert(Path::default(), key.to_vec(), Node::new_item(vec![]));
+        }
+        tree.insert(Path::default(), b"s".to_vec(), Node::new_sum_item(1));
+        let item = Element::Item { value: vec![] };
+
+        let first = jump_target(&tree, &item, true);
+        assert_eq!(first, Some((Path::default(), b"a".to_vec())));
+
+        tree.select_node(Path::default(), b"c".to_vec());
+        assert_eq!(
+            jump_target(&tree, &item, true),
+            Some((Path::default(), b"a".to_vec()))
+        );
+        assert_eq!(
+            jump_target(&tree, &item, false),
+            Some((Path::default(), b"b".to_vec()))
+        );
+
+        tree.select_node(Path::default(), b"s".to_vec());
+        assert_eq!(
+            jump_target(&tree, &item, true),
+            Some((Path::default(), b"a".to_vec()))
+        );
+    }
+}
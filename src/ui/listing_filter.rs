@@ -0,0 +1,139 @@
+//! Filter bar of the collapsed subtree listing
+
+use eframe::egui::{self, RichText};
+use strum::IntoEnumIterator;
+
+use super::{common::display_variant_combo, theme::Theme};
+use crate::model::{
+    listing::{KeyMatch, SortBy, ValuePredicate},
+    Element, SubtreeCtx,
+};
+
+/// Draw filter and sort settings of loaded keys listed in the collapsed
+/// subtree, with pages of the filtered keys if the filter is active.
+pub(crate) fn draw_listing_filter(
+    ui: &mut egui::Ui,
+    theme: &Theme,
+    subtree_ctx: SubtreeCtx,
+    page_size: usize,
+) {
+    let subtree = subtree_ctx.subtree();
+    let id = subtree_ctx.egui_id();
+
+    let mut filter = subtree.listing_filter_mut().clone();
+    let before = filter.clone();
+
+    ui.collapsing("🔍 Filter", |ui| {
+        ui.horizontal(|line| {
+            egui::ComboBox::from_id_source((id, "key_match"))
+                .width(80.)
+                .selected_text(filter.key_match.as_ref())
+                .show_ui(line, |combo| {
+                    KeyMatch::iter().for_each(|key_match| {
+                        combo.selectable_value(
+                            &mut filter.key_match,
+                            key_match,
+                            key_match.as_ref(),
+                        );
+                    });
+                });
+            line.add(
+                egui::TextEdit::singleline(&mut filter.key)
+                    .hint_text("key")
+                    .desired_width(120.),
+            );
+            display_variant_combo(line, (id, "display_variant"), &mut filter.display_variant);
+        });
+
+        ui.horizontal(|line| {
+            line.label("Type");
+            egui::ComboBox::from_id_source((id, "element_type"))
+                .selected_text(
+                    filter
+                        .element_type
+                        .as_ref()
+                        .map(|element| element.as_ref())
+                        .unwrap_or("Any"),
+                )
+                .show_ui(line, |combo| {
+                    combo.selectable_value(&mut filter.element_type, None, "Any");
+                    Element::iter().for_each(|element| {
+                        let name = element.as_ref().to_owned();
+                        combo.selectable_value(&mut filter.element_type, Some(element), name);
+                    });
+                });
+        });
+
+        ui.horizontal(|line| {
+            line.label("Value");
+            egui::ComboBox::from_id_source((id, "value_predicate"))
+                .width(80.)
+                .selected_text(filter.value_predicate.as_ref())
+                .show_ui(line, |combo| {
+                    ValuePredicate::iter().for_each(|predicate| {
+                        combo.selectable_value(
+                            &mut filter.value_predicate,
+                            predicate,
+                            predicate.as_ref(),
+                        );
+                    });
+                });
+            if filter.value_predicate != ValuePredicate::Any {
+                line.add(egui::TextEdit::singleline(&mut filter.value).desired_width(120.));
+            }
+        });
+
+        ui.horizontal(|line| {
+            line.label("Sort by");
+            egui::ComboBox::from_id_source((id, "sort_by"))
+                .selected_text(filter.sort_by.as_ref())
+                .show_ui(line, |combo| {
+                    SortBy::iter().for_each(|sort_by| {
+                        combo.selectable_value(&mut filter.sort_by, sort_by, sort_by.as_ref());
+                    });
+                });
+            line.checkbox(&mut filter.descending, "Descending");
+        });
+
+        if filter.is_active() && ui.button("Reset").clicked() {
+            filter = Default::default();
+        }
+    });
+
+    // Another filter gives another set of pages
+    if filter != before {
+        filter.page = 0;
+    }
+
+    if filter.is_active() {
+        if filter.is_malformed() {
+            ui.label(RichText::new("Bad filter input").color(theme.error()));
+        }
+        *subtree.listing_filter_mut() = filter.clone();
+        let matching = subtree_ctx.filtered_nodes().len();
+        let pages = matching.div_ceil(page_size.max(1));
+        filter.page = filter.page.min(pages.saturating_sub(1));
+        ui.horizontal(|pagination| {
+            if pagination
+                .add_enabled(filter.page > 0, egui::Button::new("⬅"))
+                .clicked()
+            {
+                filter.page -= 1;
+            }
+            if pagination
+                .add_enabled(filter.page + 1 < pages, egui::Button::new("➡"))
+                .clicked()
+            {
+                filter.page += 1;
+            }
+            pagination
+                .label(format!(
+                    "{matching} of {} loaded keys match",
+                    subtree.nodes.len()
+                ))
+                .on_hover_text("Only loaded keys are filtered, fetch more pages to find others");
+        });
+    }
+
+    *subtree.listing_filter_mut() = filter;
+}
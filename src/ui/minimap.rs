@@ -0,0 +1,92 @@
+//! Overview of the whole canvas to navigate it
+
+use eframe::{
+    egui::{self, Id, Sense},
+    emath::{RectTransform, TSTransform},
+    epaint::{Rect, Stroke, Vec2},
+};
+
+use super::theme::Theme;
+use crate::model::{
+    alignment::{NODE_HEIGHT, NODE_WIDTH},
+    Tree,
+};
+
+const MINIMAP_SIZE: Vec2 = Vec2::new(240., 160.);
+
+/// Draw visible subtrees scaled down into a corner of the canvas, the
+/// viewport rect can be moved by clicking or dragging.
+pub(crate) fn draw_minimap(
+    ui: &mut egui::Ui,
+    tree: &Tree,
+    theme: &Theme,
+    transform: &mut TSTransform,
+    canvas: Rect,
+) {
+    let Some(bounds) = tree.visible_bounds() else {
+        return;
+    };
+
+    egui::Area::new(Id::new("minimap"))
+        .anchor(egui::Align2::RIGHT_BOTTOM, [-20.0, -20.0])
+        .order(egui::Order::Foreground)
+        .show(ui.ctx(), |ui| {
+            egui::Frame::default()
+                .rounding(egui::Rounding::same(4.0))
+                .inner_margin(egui::Margin::same(4.0))
+                .stroke(ui.ctx().style().visuals.window_stroke)
+                .fill(ui.style().visuals.panel_fill)
+                .show(ui, |ui| {
+                    let (response, painter) =
+                        ui.allocate_painter(MINIMAP_SIZE, Sense::click_and_drag());
+
+                    // Keep the aspect ratio of the canvas
+                    let scale = (MINIMAP_SIZE.x / bounds.width().max(1.))
+                        .min(MINIMAP_SIZE.y / bounds.height().max(1.));
+                    let to_minimap = RectTransform::from_to(
+                        Rect::from_center_size(bounds.center(), MINIMAP_SIZE / scale),
+                        response.rect,
+                    );
+
+                    for subtree in tree.subtrees.values().filter(|s| s.visible()) {
+                        if !subtree.is_expanded() {
+                            painter.rect_filled(
+                                to_minimap.transform_rect(subtree.bounding_rect()),
+                                1.0,
+                                theme.minimap_subtree(),
+                            );
+                            continue;
+                        }
+                        for placement in subtree.expanded_layout() {
+                            let Some(node) = subtree.nodes.get(&placement.key) else {
+                                continue;
+                            };
+                            let top = subtree.position() + placement.offset;
+                            let node_rect = Rect::from_min_size(
+                                top - Vec2::new(NODE_WIDTH / 2., 0.),
+                                Vec2::new(NODE_WIDTH, NODE_HEIGHT / 2.),
+                            );
+                            painter.rect_filled(
+                                to_minimap.transform_rect(node_rect),
+                                0.0,
+                                theme.element_color(&node.element),
+                            );
+                        }
+                    }
+
+                    let viewport = to_minimap.transform_rect(transform.inverse() * canvas);
+                    painter.rect_stroke(
+                        viewport.intersect(response.rect),
+                        0.0,
+                        Stroke::new(1.0, theme.minimap_viewport()),
+                    );
+
+                    if let Some(pointer) = response.interact_pointer_pos() {
+                        // Put the picked point in the middle of the canvas
+                        let target = to_minimap.inverse().transform_pos(pointer);
+                        transform.translation =
+                            canvas.center().to_vec2() - target.to_vec2() * transform.scaling;
+                    }
+                });
+        });
+}
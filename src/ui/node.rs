@@ -1,27 +1,51 @@
 use eframe::{
-    egui,
-    epaint::{Color32, Stroke},
+    egui::{self, RichText},
+    epaint::Stroke,
 };
 use tokio::sync::mpsc::Sender;
 
-use super::common::{binary_label, bytes_by_display_variant, path_label};
+use super::{
+    camera::CameraFocus,
+    common::{binary_label_colored, bytes_as_hex, bytes_by_display_variant, path_label_colored},
+    contracts::{decoded_key_label, document_view},
+    theme::Theme,
+};
 use crate::{
     fetch::Message,
-    model::{Element, Node, NodeCtx},
+    model::{contract::Contracts, hash::CryptoHash, Element, Node, NodeCtx},
 };
 
-pub(crate) fn draw_node<'a>(ui: &mut egui::Ui, sender: &Sender<Message>, node_ctx: NodeCtx<'a>) {
+/// Children are only revealed without `sender`, for trees that can't be
+/// fetched from.
+pub(crate) fn draw_node<'a>(
+    ui: &mut egui::Ui,
+    sender: Option<&Sender<Message>>,
+    contracts: &Contracts,
+    theme: &Theme,
+    node_ctx: NodeCtx<'a>,
+) -> Option<CameraFocus> {
     let (node, _, key) = node_ctx.split();
 
+    if let Some(hash) = &node.ghost {
+        draw_ghost_node(ui, theme, node_ctx, hash);
+        return None;
+    }
+
+    let mut focus = None;
+
     let mut stroke = Stroke::default();
-    stroke.color = element_to_color(&node.element);
-    stroke.width = 1.0;
+    stroke.color = theme.element_color(&node.element);
+    stroke.width = if node_ctx.is_selected() { 3.0 } else { 1.0 };
 
     egui::Frame::default()
         .rounding(egui::Rounding::same(4.0))
         .inner_margin(egui::Margin::same(8.0))
         .stroke(stroke)
-        .fill(Color32::BLACK)
+        .fill(if node_ctx.is_query_result() {
+            theme.query_result_fill()
+        } else {
+            theme.node_fill()
+        })
         .show(ui, |ui| {
             ui.style_mut().wrap = Some(false);
 
@@ -29,10 +53,34 @@ pub(crate) fn draw_node<'a>(ui: &mut egui::Ui, sender: &Sender<Message>, node_ct
                 if menu.button("Collapse").clicked() {
                     node_ctx.subtree().set_collapsed();
                 }
+                if menu.button("Inspect").clicked() {
+                    node_ctx.select();
+                }
+                if menu.button("Center on node").clicked() {
+                    let state = node.ui_state.borrow();
+                    focus = Some(CameraFocus::Center(
+                        state.input_point.lerp(state.output_point, 0.5),
+                    ));
+                }
+                if menu.button("Zoom to subtree").clicked() {
+                    focus = Some(CameraFocus::Fit(node_ctx.subtree().bounding_rect()));
+                }
             });
 
-            binary_label(ui, key, &mut node.ui_state.borrow_mut().key_display_variant);
-            draw_element(ui, node_ctx);
+            if binary_label_colored(
+                ui,
+                key,
+                &mut node.ui_state.borrow_mut().key_display_variant,
+                theme.muted_text(),
+            )
+            .clicked()
+            {
+                node_ctx.select();
+            }
+            decoded_key_label(ui, contracts, node_ctx);
+            draw_element(ui, contracts, theme, node_ctx);
+            draw_node_metadata(ui, theme, node);
+            draw_node_hash(ui, theme, node_ctx);
 
             ui.horizontal(|footer| {
                 if footer
@@ -40,15 +88,17 @@ pub(crate) fn draw_node<'a>(ui: &mut egui::Ui, sender: &Sender<Message>, node_ct
                     .clicked()
                 {
                     node_ctx.set_left_visible();
-                    sender.blocking_send(Message::FetchNode {
-                        path: node_ctx.path().clone(),
-                        key: node_ctx
-                            .node()
-                            .left_child
-                            .as_ref()
-                            .expect("checked above")
-                            .clone(),
-                    });
+                    if let Some(sender) = sender {
+                        sender.blocking_send(Message::FetchNode {
+                            path: node_ctx.path().clone(),
+                            key: node_ctx
+                                .node()
+                                .left_child
+                                .as_ref()
+                                .expect("checked above")
+                                .clone(),
+                        });
+                    }
                 }
                 footer.label("|");
                 if footer
@@ -57,39 +107,53 @@ pub(crate) fn draw_node<'a>(ui: &mut egui::Ui, sender: &Sender<Message>, node_ct
                 {
                     node_ctx.set_right_visible();
 
-                    sender.blocking_send(Message::FetchNode {
-                        path: node_ctx.path().clone(),
-                        key: node_ctx
-                            .node()
-                            .right_child
-                            .as_ref()
-                            .expect("checked above")
-                            .clone(),
-                    });
+                    if let Some(sender) = sender {
+                        sender.blocking_send(Message::FetchNode {
+                            path: node_ctx.path().clone(),
+                            key: node_ctx
+                                .node()
+                                .right_child
+                                .as_ref()
+                                .expect("checked above")
+                                .clone(),
+                        });
+                    }
                 }
             });
         })
         .response;
+
+    focus
 }
 
-pub(crate) fn draw_element(ui: &mut egui::Ui, node_ctx: NodeCtx) {
+pub(crate) fn draw_element(
+    ui: &mut egui::Ui,
+    contracts: &Contracts,
+    theme: &Theme,
+    node_ctx: NodeCtx,
+) {
     let node = node_ctx.node();
     match &node.element {
         Element::Item { value } => {
-            binary_label(
+            binary_label_colored(
                 ui,
                 value,
                 &mut node.ui_state.borrow_mut().item_display_variant,
+                theme.muted_text(),
             );
+            if let Some(document) = contracts.decode_item(node_ctx.path(), value) {
+                document_view(ui, theme, node_ctx.egui_id(), document);
+            }
         }
         Element::SumItem { value } => {
             ui.label(format!("Value: {value}"));
         }
         Element::Reference { path, key } => {
-            path_label(
+            path_label_colored(
                 ui,
                 path,
                 &mut node.ui_state.borrow_mut().item_display_variant,
+                theme.muted_text(),
             );
             ui.horizontal(|line| {
                 line.add_space(20.0);
@@ -98,6 +162,7 @@ pub(crate) fn draw_element(ui: &mut egui::Ui, node_ctx: NodeCtx) {
                     &mut node.ui_state.borrow_mut().item_display_variant,
                 ));
             });
+            reference_status_label(ui, theme, node_ctx);
         }
         Element::Sumtree { sum, .. } => {
             let subtree_ctx = node_ctx.subtree_ctx();
@@ -108,6 +173,7 @@ pub(crate) fn draw_element(ui: &mut egui::Ui, node_ctx: NodeCtx) {
                 subtree_ctx.set_child_visibility(node_ctx.key(), visibility);
             }
             ui.label(format!("Sum: {sum}"));
+            sum_verification_label(ui, theme, node_ctx);
         }
         Element::Subtree { .. } => {
             let subtree_ctx = node_ctx.subtree_ctx();
@@ -132,13 +198,125 @@ pub(crate) fn draw_element(ui: &mut egui::Ui, node_ctx: NodeCtx) {
     }
 }
 
-pub(crate) fn element_to_color(element: &Element) -> Color32 {
-    match element {
-        Element::Item { .. } => Color32::WHITE,
-        Element::SumItem { .. } => Color32::DARK_GREEN,
-        Element::Reference { .. } => Color32::LIGHT_BLUE,
-        Element::Subtree { .. } => Color32::GOLD,
-        Element::SubtreePlaceholder => Color32::RED,
-        Element::Sumtree { .. } => Color32::GREEN,
+/// Show element flags and the node's contribution to a sumtree sum
+pub(crate) fn draw_node_metadata(ui: &mut egui::Ui, theme: &Theme, node: &Node) {
+    if let Some(flags) = &node.element_flags {
+        ui.horizontal(|line| {
+            line.label(RichText::new("Flags:").color(theme.muted_text()));
+            binary_label_colored(
+                line,
+                flags,
+                &mut node.ui_state.borrow_mut().flags_display_variant,
+                theme.muted_text(),
+            );
+        });
+    }
+    if let Some(sum) = node.sum_contribution() {
+        ui.label(RichText::new(format!("Σ {sum}")).color(theme.muted_text()))
+            .on_hover_text("Contribution to the sumtree sum");
+    }
+}
+
+/// Draw a node known only by its hash, like a pruned branch of a proof
+fn draw_ghost_node(ui: &mut egui::Ui, theme: &Theme, node_ctx: NodeCtx, hash: &CryptoHash) {
+    let node = node_ctx.node();
+
+    let mut stroke = Stroke::default();
+    stroke.color = theme.ghost();
+    stroke.width = if node_ctx.is_selected() { 3.0 } else { 1.0 };
+
+    egui::Frame::default()
+        .rounding(egui::Rounding::same(4.0))
+        .inner_margin(egui::Margin::same(8.0))
+        .stroke(stroke)
+        .fill(theme.node_fill().gamma_multiply(0.6))
+        .show(ui, |ui| {
+            ui.style_mut().wrap = Some(false);
+
+            // Digest nodes have a key but no value
+            if node.value_hash.is_some()
+                && binary_label_colored(
+                    ui,
+                    node_ctx.key(),
+                    &mut node.ui_state.borrow_mut().key_display_variant,
+                    theme.muted_text(),
+                )
+                .clicked()
+            {
+                node_ctx.select();
+            }
+            if ui
+                .add(
+                    egui::Label::new(
+                        RichText::new(format!("# {}", bytes_as_hex(hash))).color(theme.ghost()),
+                    )
+                    .sense(egui::Sense::click()),
+                )
+                .on_hover_text("Hash only node")
+                .clicked()
+            {
+                node_ctx.select();
+            }
+            hash_mismatch_label(ui, theme, node_ctx);
+        });
+}
+
+/// Show the node hash and warn if hashes received from GroveDB don't match
+/// locally computed ones.
+pub(crate) fn draw_node_hash(ui: &mut egui::Ui, theme: &Theme, node_ctx: NodeCtx) {
+    let node = node_ctx.node();
+    if let Some(hash) = node.node_hash() {
+        ui.label(RichText::new(format!("# {}", bytes_as_hex(&hash))).color(theme.muted_text()));
+    }
+    hash_mismatch_label(ui, theme, node_ctx);
+}
+
+pub(crate) fn hash_mismatch_label(ui: &mut egui::Ui, theme: &Theme, node_ctx: NodeCtx) {
+    let mismatches = node_ctx
+        .node()
+        .verify_hashes(node_ctx.subtree(), node_ctx.key());
+    if !mismatches.is_empty() {
+        ui.colored_label(theme.error(), "⚠ Hash mismatch")
+            .on_hover_ui(|hover| {
+                mismatches.iter().for_each(|m| {
+                    hover.label(m.to_string());
+                });
+            });
+    }
+}
+
+/// Warn if a sumtree's sum doesn't match its loaded contents or is close to
+/// overflow.
+pub(crate) fn sum_verification_label(ui: &mut egui::Ui, theme: &Theme, node_ctx: NodeCtx) {
+    let Some(verification) = node_ctx
+        .subtree_ctx()
+        .tree()
+        .verify_sum(node_ctx.path(), node_ctx.key())
+    else {
+        return;
+    };
+    if !verification.is_consistent() {
+        ui.colored_label(theme.error(), "⚠ Sum mismatch")
+            .on_hover_text(format!(
+                "Sum of loaded child subtree items is {}",
+                verification.computed
+            ));
+    }
+    if verification.overflow_risk() {
+        ui.colored_label(theme.warning(), "⚠ Near i64 bounds")
+            .on_hover_text("Sum is close to overflow");
+    }
+}
+
+/// Warn if a reference target turned out to be missing or a subtree.
+pub(crate) fn reference_status_label(ui: &mut egui::Ui, theme: &Theme, node_ctx: NodeCtx) {
+    if let Some(status) = node_ctx
+        .subtree_ctx()
+        .tree()
+        .reference_status(node_ctx.path(), node_ctx.key())
+        .filter(|status| status.is_broken())
+    {
+        ui.colored_label(theme.error(), "⚠ Broken reference")
+            .on_hover_text(status.to_string());
     }
 }
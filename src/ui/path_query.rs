@@ -0,0 +1,397 @@
+//! Path query builder to run GroveDB path queries through the debugger and
+//! list their results
+
+use eframe::{
+    egui::{self, RichText},
+    epaint::Color32,
+};
+use grovedbg_types::{PathQuery, Query, QueryItem, SizedQuery, SubqueryBranch};
+use strum::IntoEnumIterator;
+use tokio::sync::mpsc::Sender;
+
+use super::{
+    common::{
+        binary_label, bytes_by_display_variant, bytes_from_display_variant, display_variant_combo,
+        path_label,
+    },
+    theme::Theme,
+    DisplayVariant,
+};
+use crate::{
+    fetch::Message,
+    model::{Element, Tree},
+};
+
+/// Binary input with a representation to parse it with
+#[derive(Default)]
+struct BytesInput {
+    text: String,
+    display_variant: DisplayVariant,
+}
+
+impl BytesInput {
+    fn parse(&self, what: &str) -> Result<Vec<u8>, String> {
+        bytes_from_display_variant(&self.text, self.display_variant)
+            .ok_or_else(|| format!("Bad {what}: {:?}", self.text))
+    }
+
+    fn show(&mut self, ui: &mut egui::Ui, hint: &str) {
+        display_variant_combo(ui, hint, &mut self.display_variant);
+        ui.add(
+            egui::TextEdit::singleline(&mut self.text)
+                .hint_text(hint)
+                .desired_width(120.),
+        );
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, strum::EnumIter, strum::AsRefStr)]
+enum QueryItemKind {
+    #[default]
+    Key,
+    Range,
+    RangeInclusive,
+    RangeFull,
+    RangeFrom,
+    RangeTo,
+    RangeToInclusive,
+    RangeAfter,
+    RangeAfterTo,
+    RangeAfterToInclusive,
+}
+
+impl QueryItemKind {
+    fn bound_names(&self) -> &'static [&'static str] {
+        match self {
+            QueryItemKind::Key => &["key"],
+            QueryItemKind::Range | QueryItemKind::RangeInclusive => &["start", "end"],
+            QueryItemKind::RangeFull => &[],
+            QueryItemKind::RangeFrom => &["start"],
+            QueryItemKind::RangeTo | QueryItemKind::RangeToInclusive => &["end"],
+            QueryItemKind::RangeAfter => &["after"],
+            QueryItemKind::RangeAfterTo | QueryItemKind::RangeAfterToInclusive => &["after", "to"],
+        }
+    }
+}
+
+#[derive(Default)]
+struct QueryItemInput {
+    kind: QueryItemKind,
+    bounds: [BytesInput; 2],
+}
+
+impl QueryItemInput {
+    fn to_query_item(&self) -> Result<QueryItem, String> {
+        let names = self.kind.bound_names();
+        let bound = |i: usize| self.bounds[i].parse(names[i]);
+        Ok(match self.kind {
+            QueryItemKind::Key => QueryItem::Key(bound(0)?),
+            QueryItemKind::Range => QueryItem::Range {
+                start: bound(0)?,
+                end: bound(1)?,
+            },
+            QueryItemKind::RangeInclusive => QueryItem::RangeInclusive {
+                start: bound(0)?,
+                end: bound(1)?,
+            },
+            QueryItemKind::RangeFull => QueryItem::RangeFull,
+            QueryItemKind::RangeFrom => QueryItem::RangeFrom(bound(0)?),
+            QueryItemKind::RangeTo => QueryItem::RangeTo(bound(0)?),
+            QueryItemKind::RangeToInclusive => QueryItem::RangeToInclusive(bound(0)?),
+            QueryItemKind::RangeAfter => QueryItem::RangeAfter(bound(0)?),
+            QueryItemKind::RangeAfterTo => QueryItem::RangeAfterTo {
+                after: bound(0)?,
+                to: bound(1)?,
+            },
+            QueryItemKind::RangeAfterToInclusive => QueryItem::RangeAfterToInclusive {
+                after: bound(0)?,
+                to: bound(1)?,
+            },
+        })
+    }
+}
+
+struct QueryInput {
+    items: Vec<QueryItemInput>,
+    left_to_right: bool,
+    subquery_path: Vec<BytesInput>,
+    subquery: Option<Box<QueryInput>>,
+}
+
+impl Default for QueryInput {
+    fn default() -> Self {
+        QueryInput {
+            items: Vec::new(),
+            left_to_right: true,
+            subquery_path: Vec::new(),
+            subquery: None,
+        }
+    }
+}
+
+impl QueryInput {
+    fn to_query(&self) -> Result<Query, String> {
+        let subquery_path = parse_path(&self.subquery_path, "subquery path segment")?;
+        Ok(Query {
+            items: self
+                .items
+                .iter()
+                .map(QueryItemInput::to_query_item)
+                .collect::<Result<_, _>>()?,
+            default_subquery_branch: SubqueryBranch {
+                subquery_path: (!subquery_path.is_empty()).then_some(subquery_path),
+                subquery: self
+                    .subquery
+                    .as_ref()
+                    .map(|q| q.to_query().map(Box::new))
+                    .transpose()?,
+            },
+            conditional_subquery_branches: Vec::new(),
+            left_to_right: self.left_to_right,
+        })
+    }
+}
+
+#[derive(Default)]
+struct PathQueryInput {
+    path: Vec<BytesInput>,
+    query: QueryInput,
+    limit: String,
+    offset: String,
+}
+
+impl PathQueryInput {
+    fn to_path_query(&self) -> Result<PathQuery, String> {
+        let parse_number = |text: &str, what: &str| {
+            let text = text.trim();
+            (!text.is_empty())
+                .then(|| text.parse().map_err(|_| format!("Bad {what}: {text:?}")))
+                .transpose()
+        };
+        Ok(PathQuery {
+            path: parse_path(&self.path, "path segment")?,
+            query: SizedQuery {
+                query: self.query.to_query()?,
+                limit: parse_number(&self.limit, "limit")?,
+                offset: parse_number(&self.offset, "offset")?,
+            },
+        })
+    }
+}
+
+fn parse_path(segments: &[BytesInput], what: &str) -> Result<Vec<Vec<u8>>, String> {
+    segments.iter().map(|segment| segment.parse(what)).collect()
+}
+
+fn path_ui(ui: &mut egui::Ui, segments: &mut Vec<BytesInput>, hint: &str) {
+    let mut to_remove = None;
+    for (i, segment) in segments.iter_mut().enumerate() {
+        ui.push_id(i, |ui| {
+            ui.horizontal(|line| {
+                if line.small_button("🗑").clicked() {
+                    to_remove = Some(i);
+                }
+                segment.show(line, hint);
+            });
+        });
+    }
+    if let Some(i) = to_remove {
+        segments.remove(i);
+    }
+    if ui.small_button(format!("Add {hint}")).clicked() {
+        segments.push(Default::default());
+    }
+}
+
+fn query_ui(ui: &mut egui::Ui, query: &mut QueryInput) {
+    ui.label(RichText::new("Items").strong());
+    let mut to_remove = None;
+    for (i, item) in query.items.iter_mut().enumerate() {
+        ui.push_id(("item", i), |ui| {
+            ui.horizontal(|line| {
+                if line.small_button("🗑").clicked() {
+                    to_remove = Some(i);
+                }
+                egui::ComboBox::from_id_source("kind")
+                    .selected_text(item.kind.as_ref())
+                    .show_ui(line, |combo| {
+                        QueryItemKind::iter().for_each(|kind| {
+                            combo.selectable_value(&mut item.kind, kind, kind.as_ref());
+                        });
+                    });
+                for (bound, name) in item.bounds.iter_mut().zip(item.kind.bound_names()) {
+                    bound.show(line, name);
+                }
+            });
+        });
+    }
+    if let Some(i) = to_remove {
+        query.items.remove(i);
+    }
+    if ui.small_button("Add item").clicked() {
+        query.items.push(Default::default());
+    }
+    ui.checkbox(&mut query.left_to_right, "Left to right");
+
+    ui.label(RichText::new("Subquery path").strong());
+    ui.push_id("subquery_path", |ui| {
+        path_ui(ui, &mut query.subquery_path, "segment");
+    });
+
+    let mut has_subquery = query.subquery.is_some();
+    ui.checkbox(&mut has_subquery, "Subquery");
+    match (has_subquery, &mut query.subquery) {
+        (true, None) => query.subquery = Some(Default::default()),
+        (false, Some(_)) => query.subquery = None,
+        _ => {}
+    }
+    if let Some(subquery) = &mut query.subquery {
+        ui.push_id("subquery", |ui| {
+            ui.group(|group| query_ui(group, subquery));
+        });
+    }
+}
+
+/// Window to build and run a path query and to list its results
+#[derive(Default)]
+pub(crate) struct PathQueryWindow {
+    open: bool,
+    input: PathQueryInput,
+    error: Option<String>,
+    results_display_variant: DisplayVariant,
+}
+
+impl PathQueryWindow {
+    pub(crate) fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+
+    pub(crate) fn show(
+        &mut self,
+        ctx: &egui::Context,
+        tree: &mut Tree,
+        sender: &Sender<Message>,
+        theme: &Theme,
+    ) {
+        let mut open = self.open;
+        egui::Window::new("Path query")
+            .open(&mut open)
+            .default_width(500.)
+            .show(ctx, |ui| {
+                egui::ScrollArea::vertical()
+                    .id_source("path_query_builder")
+                    .max_height(400.)
+                    .show(ui, |ui| {
+                        ui.label(RichText::new("Path").strong());
+                        ui.push_id("path", |ui| {
+                            path_ui(ui, &mut self.input.path, "segment");
+                        });
+                        ui.separator();
+                        query_ui(ui, &mut self.input.query);
+                        ui.separator();
+                        ui.horizontal(|line| {
+                            line.label("Limit");
+                            line.add(
+                                egui::TextEdit::singleline(&mut self.input.limit)
+                                    .desired_width(50.),
+                            );
+                            line.label("Offset");
+                            line.add(
+                                egui::TextEdit::singleline(&mut self.input.offset)
+                                    .desired_width(50.),
+                            );
+                        });
+                    });
+
+                ui.horizontal(|line| {
+                    if line.button("Run").clicked() {
+                        match self.input.to_path_query() {
+                            Ok(path_query) => {
+                                self.error = None;
+                                // TODO error handling
+                                let _ = sender
+                                    .blocking_send(Message::FetchWithPathQuery { path_query });
+                            }
+                            Err(e) => self.error = Some(e),
+                        }
+                    }
+                    if line.button("Copy as JSON").clicked() {
+                        match self.input.to_path_query() {
+                            Ok(path_query) => {
+                                self.error = None;
+                                line.ctx().copy_text(
+                                    serde_json::to_string_pretty(&path_query)
+                                        .expect("path query is serializable"),
+                                );
+                            }
+                            Err(e) => self.error = Some(e),
+                        }
+                    }
+                    if line.button("Clear results").clicked() {
+                        tree.set_query_results(Vec::new());
+                    }
+                });
+                if let Some(error) = &self.error {
+                    ui.colored_label(Color32::RED, error);
+                }
+                ui.separator();
+
+                self.results_table(ui, tree, theme);
+            });
+        self.open = open;
+    }
+
+    fn results_table(&mut self, ui: &mut egui::Ui, tree: &Tree, theme: &Theme) {
+        ui.label(format!("{} results", tree.query_results().len()));
+        egui::ScrollArea::both()
+            .id_source("path_query_results")
+            .max_height(300.)
+            .show(ui, |ui| {
+                egui::Grid::new("path_query_results_grid")
+                    .striped(true)
+                    .show(ui, |grid| {
+                        for (path, key) in tree.query_results() {
+                            path_label(grid, path, &mut self.results_display_variant);
+                            binary_label(grid, key, &mut self.results_display_variant);
+                            match tree.get_node(path, key) {
+                                Some(node) => {
+                                    grid.label(
+                                        RichText::new(node.element.as_ref())
+                                            .color(theme.element_color(&node.element)),
+                                    );
+                                    grid.label(element_summary(
+                                        &node.element,
+                                        &self.results_display_variant,
+                                    ));
+                                }
+                                None => {
+                                    grid.label("Not loaded");
+                                    grid.label("");
+                                }
+                            }
+                            if grid.small_button("Inspect").clicked() {
+                                tree.select_node(path.clone(), key.clone());
+                            }
+                            grid.end_row();
+                        }
+                    });
+            });
+    }
+}
+
+fn element_summary(element: &Element, display_variant: &DisplayVariant) -> String {
+    match element {
+        Element::Item { value } => bytes_by_display_variant(value, display_variant),
+        Element::SumItem { value } => value.to_string(),
+        Element::Reference { path, key } => {
+            let mut segments: Vec<_> = path
+                .iter()
+                .map(|segment| bytes_by_display_variant(segment, display_variant))
+                .collect();
+            segments.push(bytes_by_display_variant(key, display_variant));
+            format!("→ {}", segments.join("/"))
+        }
+        Element::Sumtree { sum, .. } => format!("Sum: {sum}"),
+        Element::Subtree { .. } | Element::SubtreePlaceholder => String::new(),
+    }
+}
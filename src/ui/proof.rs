@@ -0,0 +1,200 @@
+//! GroveDB proof loading, verification and the proof tree state
+
+use base64::Engine;
+use eframe::{
+    egui::{self, emath::TSTransform, RichText},
+    epaint::Color32,
+};
+
+use super::common::bytes_as_base64;
+use crate::model::{
+    hash::CryptoHash,
+    proof::{GroveDbProof, LayerMismatch},
+    Tree,
+};
+
+/// Results of a loaded proof verification
+struct Verification {
+    root_hash: CryptoHash,
+    layer_mismatches: Vec<LayerMismatch>,
+}
+
+/// Window to paste or drop a serialized proof into, the decoded proof is kept
+/// as a separate [Tree] with its own transform to be drawn instead of the
+/// database tree.
+#[derive(Default)]
+pub(crate) struct ProofViewer {
+    open: bool,
+    input: String,
+    expected_root_hash: String,
+    verification: Option<Result<Verification, String>>,
+    pub(crate) tree: Tree,
+    pub(crate) transform: TSTransform,
+}
+
+impl ProofViewer {
+    pub(crate) fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+
+    fn load(&mut self, bytes: &[u8]) {
+        self.verification = Some(
+            GroveDbProof::decode(bytes)
+                .map(|proof| {
+                    self.tree = proof.to_tree();
+                    self.transform = TSTransform::default();
+                    Verification {
+                        root_hash: proof.root_hash(),
+                        layer_mismatches: proof.verify_layers(),
+                    }
+                })
+                .map_err(|e| e.to_string()),
+        );
+    }
+
+    /// Show the window, returns `true` if a new proof was loaded.
+    pub(crate) fn show(&mut self, ctx: &egui::Context) -> bool {
+        let mut loaded = false;
+
+        // Proof files can be dropped anywhere
+        for file in ctx.input(|i| i.raw.dropped_files.clone()) {
+            let bytes = match (file.bytes, file.path) {
+                (Some(bytes), _) => bytes.to_vec(),
+                #[cfg(not(target_arch = "wasm32"))]
+                (None, Some(path)) => match std::fs::read(path) {
+                    Ok(bytes) => bytes,
+                    Err(e) => {
+                        self.verification = Some(Err(e.to_string()));
+                        continue;
+                    }
+                },
+                _ => continue,
+            };
+            // A file may contain either raw bytes or their text encoding
+            match std::str::from_utf8(&bytes)
+                .ok()
+                .and_then(|text| parse_proof_input(text).ok())
+            {
+                Some(decoded) => self.load(&decoded),
+                None => self.load(&bytes),
+            }
+            self.open = true;
+            loaded = true;
+        }
+
+        let mut open = self.open;
+        egui::Window::new("Proof")
+            .open(&mut open)
+            .default_width(400.)
+            .show(ctx, |ui| {
+                egui::ScrollArea::vertical()
+                    .max_height(200.)
+                    .show(ui, |ui| {
+                        ui.add(
+                            egui::TextEdit::multiline(&mut self.input)
+                                .hint_text("Serialized proof as hex or Base64, or drop a file")
+                                .code_editor()
+                                .desired_width(f32::INFINITY),
+                        );
+                    });
+
+                if ui.button("Load").clicked() {
+                    match parse_proof_input(&self.input) {
+                        Ok(bytes) => {
+                            self.load(&bytes);
+                            loaded = true;
+                        }
+                        Err(e) => self.verification = Some(Err(e)),
+                    }
+                }
+                ui.separator();
+
+                ui.horizontal(|line| {
+                    line.label("Expected root hash");
+                    line.add(
+                        egui::TextEdit::singleline(&mut self.expected_root_hash)
+                            .hint_text("Hex")
+                            .desired_width(f32::INFINITY),
+                    );
+                });
+
+                match &self.verification {
+                    Some(Ok(verification)) => {
+                        ui.horizontal(|line| {
+                            line.label("Computed root hash");
+                            line.label(
+                                RichText::new(hex::encode(verification.root_hash)).monospace(),
+                            );
+                            if line.small_button("📋").clicked() {
+                                line.ctx().copy_text(hex::encode(verification.root_hash));
+                            }
+                        });
+                        ui.label(
+                            RichText::new(bytes_as_base64(&verification.root_hash))
+                                .small()
+                                .color(Color32::GRAY),
+                        );
+
+                        let expected = self.expected_root_hash.trim();
+                        if !expected.is_empty() {
+                            match hex::decode(expected) {
+                                Ok(expected) if expected == verification.root_hash => {
+                                    ui.colored_label(Color32::GREEN, "✔ Root hash matches");
+                                }
+                                Ok(expected) if expected.len() == 32 => {
+                                    ui.colored_label(Color32::RED, "✘ Root hash doesn't match");
+                                }
+                                _ => {
+                                    ui.colored_label(Color32::RED, "Not a 32 bytes hex hash");
+                                }
+                            }
+                        }
+
+                        if verification.layer_mismatches.is_empty() {
+                            ui.colored_label(
+                                Color32::GREEN,
+                                "✔ All layers are bound to their subtree elements",
+                            );
+                        }
+                        for mismatch in verification.layer_mismatches.iter() {
+                            let path = mismatch
+                                .path
+                                .iter()
+                                .map(hex::encode)
+                                .collect::<Vec<_>>()
+                                .join("/");
+                            ui.colored_label(
+                                Color32::RED,
+                                format!(
+                                    "✘ /{path} key {}: {}",
+                                    hex::encode(&mismatch.key),
+                                    mismatch.reason
+                                ),
+                            );
+                        }
+                    }
+                    Some(Err(e)) => {
+                        ui.colored_label(Color32::RED, format!("Unable to load proof: {e}"));
+                    }
+                    None => {
+                        ui.label("No proof loaded");
+                    }
+                }
+            });
+        self.open = open;
+
+        loaded
+    }
+}
+
+/// Decode hex, possibly `0x` prefixed, or Base64 ignoring whitespace.
+fn parse_proof_input(input: &str) -> Result<Vec<u8>, String> {
+    let compact: String = input.chars().filter(|c| !c.is_whitespace()).collect();
+    let compact = compact.strip_prefix("0x").unwrap_or(&compact);
+    if compact.is_empty() {
+        return Err("empty input".to_owned());
+    }
+    hex::decode(compact)
+        .or_else(|_| base64::engine::general_purpose::STANDARD.decode(compact))
+        .map_err(|_| "input is neither hex nor Base64".to_owned())
+}
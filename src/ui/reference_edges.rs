@@ -0,0 +1,259 @@
+//! Curved edges from references to their targets
+
+use std::collections::HashMap;
+
+use eframe::{
+    egui::{self, Id},
+    emath::TSTransform,
+    epaint::{Color32, CubicBezierShape, Pos2, Stroke, Vec2},
+};
+
+use super::{common::path_with_key, theme::Theme};
+use crate::model::{Key, Path, Tree};
+
+/// Max distance from the pointer to an edge to consider it hovered
+const HOVER_DISTANCE: f32 = 6.0;
+const ARROW_LENGTH: f32 = 8.0;
+
+/// Which reference edges to draw
+#[derive(Debug, Clone, Copy, Default, PartialEq, strum::EnumIter, strum::AsRefStr)]
+pub(crate) enum ReferenceEdgesMode {
+    #[default]
+    All,
+    #[strum(serialize = "Selected node")]
+    SelectedNode,
+    Hidden,
+}
+
+/// Edge from a reference node to its target, the target point is looked up
+/// after all nodes are drawn.
+pub(crate) struct ReferenceEdge {
+    pub(crate) from: Pos2,
+    /// Direction the edge leaves its source in, down for nodes and right for
+    /// rows of collapsed subtrees
+    pub(crate) direction: Vec2,
+    pub(crate) source: (Path, Key),
+    pub(crate) target: (Path, Key),
+    /// Row of the target when it's listed in the same collapsed subtree, the
+    /// edge is drawn as an arc on the subtree's side then
+    pub(crate) to: Option<Pos2>,
+}
+
+/// Curve of an edge, `bundle_from` is shared by edges of one bundle to make
+/// them converge.
+pub(super) fn edge_curve(from: Pos2, bundle_from: Pos2, direction: Vec2, to: Pos2) -> [Pos2; 4] {
+    let bend = (from.distance(to) / 3.).clamp(30., 200.);
+    [
+        from,
+        bundle_from + direction * bend,
+        to - Vec2::new(0., bend),
+        to,
+    ]
+}
+
+/// Curve between two rows of a collapsed subtree going around its right side
+pub(super) fn side_arc(from: Pos2, to: Pos2) -> [Pos2; 4] {
+    let bend = ((to.y - from.y).abs() / 2.).clamp(20., 100.);
+    let x = from.x.max(to.x) + bend;
+    [from, Pos2::new(x, from.y), Pos2::new(x, to.y), to]
+}
+
+/// Two segments of the arrow tip at the end of the curve
+pub(super) fn arrow_tip(points: [Pos2; 4]) -> [[Pos2; 2]; 2] {
+    let [.., control, to] = points;
+    let tip_direction = (to - control).normalized() * ARROW_LENGTH;
+    let rotation = egui::emath::Rot2::from_angle(0.4);
+    [
+        [to, to - rotation * tip_direction],
+        [to, to - rotation.inverse() * tip_direction],
+    ]
+}
+
+fn draw_curve(painter: &egui::Painter, points: [Pos2; 4], stroke: Stroke) {
+    painter.add(CubicBezierShape::from_points_stroke(
+        points,
+        false,
+        Color32::TRANSPARENT,
+        stroke,
+    ));
+    for segment in arrow_tip(points) {
+        painter.line_segment(segment, stroke);
+    }
+}
+
+fn distance_to_curve(points: [Pos2; 4], pointer: Pos2) -> f32 {
+    let polyline =
+        CubicBezierShape::from_points_stroke(points, false, Color32::TRANSPARENT, Stroke::NONE)
+            .flatten(Some(1.0));
+    polyline
+        .windows(2)
+        .map(|segment| {
+            let (a, b) = (segment[0], segment[1]);
+            let t =
+                ((pointer - a).dot(b - a) / (b - a).length_sq().max(f32::EPSILON)).clamp(0., 1.);
+            pointer.distance(a + (b - a) * t)
+        })
+        .fold(f32::INFINITY, f32::min)
+}
+
+/// Source subtree, edge direction and target point of bundled edges
+type BundleKey = (Path, [i32; 2], [i32; 2]);
+
+/// Draw reference edges, edges with the same source subtree and target point
+/// are bundled. The edge under the pointer is highlighted with its source and
+/// target shown in a tooltip.
+pub(crate) fn draw_reference_edges(
+    ui: &mut egui::Ui,
+    transform: TSTransform,
+    tree: &Tree,
+    theme: &Theme,
+    edges: Vec<ReferenceEdge>,
+    mode: ReferenceEdgesMode,
+) {
+    let mut bundles: HashMap<BundleKey, Vec<(ReferenceEdge, Pos2)>> = HashMap::new();
+    for edge in edges {
+        let visible = match mode {
+            ReferenceEdgesMode::All => true,
+            ReferenceEdgesMode::SelectedNode => {
+                tree.is_selected(&edge.source.0, &edge.source.1)
+                    || tree.is_selected(&edge.target.0, &edge.target.1)
+            }
+            ReferenceEdgesMode::Hidden => false,
+        };
+        let Some(to) = visible
+            .then(|| {
+                edge.to.or_else(|| {
+                    tree.subtrees
+                        .get(&edge.target.0)?
+                        .get_node_input(&edge.target.1)
+                })
+            })
+            .flatten()
+        else {
+            continue;
+        };
+        bundles
+            .entry((
+                edge.source.0.clone(),
+                [edge.direction.x as i32, edge.direction.y as i32],
+                [to.x as i32, to.y as i32],
+            ))
+            .or_default()
+            .push((edge, to));
+    }
+
+    let curves: Vec<_> = bundles
+        .values()
+        .flat_map(|bundle| {
+            let centroid = bundle
+                .iter()
+                .fold(Vec2::ZERO, |sum, (edge, _)| sum + edge.from.to_vec2())
+                / bundle.len() as f32;
+            bundle.iter().enumerate().map(move |(i, (edge, to))| {
+                let points = if edge.to.is_some() {
+                    side_arc(edge.from, *to)
+                } else {
+                    edge_curve(edge.from, centroid.to_pos2(), edge.direction, *to)
+                };
+                (
+                    edge,
+                    points,
+                    // Bundle size is shown once
+                    (i == 0 && bundle.len() > 1).then_some(bundle.len()),
+                )
+            })
+        })
+        .collect();
+
+    let pointer = ui
+        .ctx()
+        .pointer_hover_pos()
+        .map(|pointer| transform.inverse() * pointer);
+    let hovered = pointer.and_then(|pointer| {
+        curves
+            .iter()
+            .enumerate()
+            .map(|(i, (_, points, _))| (i, distance_to_curve(*points, pointer)))
+            .filter(|(_, distance)| *distance <= HOVER_DISTANCE / transform.scaling)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(i, _)| i)
+    });
+
+    let painter = ui.painter();
+    for (i, (_, points, bundle_size)) in curves.iter().enumerate() {
+        if Some(i) != hovered {
+            draw_curve(painter, *points, Stroke::new(1.0, theme.reference_edge()));
+        }
+        if let Some(bundle_size) = bundle_size {
+            painter.text(
+                points[3] - Vec2::new(0., 2. * ARROW_LENGTH),
+                egui::Align2::LEFT_BOTTOM,
+                format!("×{bundle_size}"),
+                egui::FontId::monospace(10.0),
+                theme.reference_edge(),
+            );
+        }
+    }
+
+    if let Some((edge, points, _)) = hovered.map(|i| &curves[i]) {
+        draw_curve(painter, *points, Stroke::new(3.0, theme.hovered_edge()));
+        egui::show_tooltip_at_pointer(ui.ctx(), Id::new("reference_edge_tooltip"), |tooltip| {
+            tooltip.label(format!(
+                "Reference: {}",
+                path_with_key(&edge.source.0, &edge.source.1)
+            ));
+            tooltip.label(format!(
+                "Target: {}",
+                path_with_key(&edge.target.0, &edge.target.1)
+            ));
+        });
+    }
+}
+
+/// Draw edges from all loaded references pointing to the node.
+pub(crate) fn draw_incoming_edges(
+    ui: &mut egui::Ui,
+    tree: &Tree,
+    theme: &Theme,
+    path: &Path,
+    key: &[u8],
+) {
+    let Some(to) = tree
+        .subtrees
+        .get(path)
+        .and_then(|subtree| subtree.get_node_input(key))
+    else {
+        return;
+    };
+    let painter = ui.painter();
+    for (ref_path, ref_key) in tree.referrers(path, key) {
+        let Some(from) = tree
+            .subtrees
+            .get(&ref_path)
+            .and_then(|subtree| subtree.get_node_output(&ref_key))
+        else {
+            continue;
+        };
+        draw_curve(
+            painter,
+            edge_curve(from, from, Vec2::DOWN, to),
+            Stroke::new(2.0, theme.incoming_edge()),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn side_arc_goes_around_the_right() {
+        let points = side_arc(Pos2::new(100., 10.), Pos2::new(80., 50.));
+        assert!(points[1].x > 100. && points[2].x > 100.);
+        assert_eq!(points[2].y, 50.);
+        // Arrow tip points into the target row from the right
+        assert!(arrow_tip(points)
+            .iter()
+            .all(|[to, end]| *to == points[3] && end.x > to.x));
+    }
+}
@@ -0,0 +1,117 @@
+//! Background resolution of references targets and the list of broken ones
+
+use std::time::Duration;
+
+use eframe::{
+    egui::{self, RichText},
+    epaint::Color32,
+};
+use tokio::sync::mpsc::Sender;
+
+use super::{
+    common::{binary_label, path_label},
+    DisplayVariant,
+};
+use crate::{fetch::Message, model::Tree};
+
+/// Seconds between sending batches of targets to resolve
+const RESOLVE_INTERVAL: f64 = 0.5;
+/// Max number of targets sent to resolve at once
+const RESOLVE_BATCH: usize = 5;
+
+pub(crate) struct ReferencesWindow {
+    open: bool,
+    /// Resolve references targets in background
+    resolve: bool,
+    last_dispatch: f64,
+    display_variant: DisplayVariant,
+}
+
+impl Default for ReferencesWindow {
+    fn default() -> Self {
+        Self {
+            open: false,
+            resolve: true,
+            last_dispatch: 0.,
+            display_variant: Default::default(),
+        }
+    }
+}
+
+impl ReferencesWindow {
+    pub(crate) fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+
+    /// Send a batch of unresolved targets to the fetch layer if enough time
+    /// passed since the previous one, shall be called every frame.
+    pub(crate) fn dispatch(
+        &mut self,
+        ctx: &egui::Context,
+        tree: &mut Tree,
+        sender: &Sender<Message>,
+    ) {
+        if !self.resolve {
+            return;
+        }
+        let now = ctx.input(|i| i.time);
+        if now - self.last_dispatch >= RESOLVE_INTERVAL {
+            self.last_dispatch = now;
+            let limit = sender.capacity().min(RESOLVE_BATCH);
+            for (path, key) in tree.take_unresolved_targets(limit) {
+                // Capacity is checked above
+                let _ = sender.try_send(Message::ResolveReference { path, key });
+            }
+        }
+        // Keep going even if nothing happens on the screen
+        ctx.request_repaint_after(Duration::from_secs_f64(RESOLVE_INTERVAL));
+    }
+
+    pub(crate) fn show(&mut self, ctx: &egui::Context, tree: &mut Tree) {
+        let mut open = self.open;
+        egui::Window::new("References")
+            .open(&mut open)
+            .default_width(500.)
+            .show(ctx, |ui| {
+                ui.horizontal(|line| {
+                    line.checkbox(&mut self.resolve, "Resolve in background");
+                    if line.button("Recheck").clicked() {
+                        tree.reset_reference_targets();
+                    }
+                });
+
+                let broken = tree.broken_references();
+                let pending = tree.pending_targets_count();
+                if pending > 0 {
+                    ui.label(format!("{pending} targets are being resolved"));
+                }
+                if broken.is_empty() {
+                    ui.colored_label(Color32::GREEN, "✔ No broken references found");
+                } else {
+                    ui.colored_label(
+                        Color32::RED,
+                        format!("{} broken references found", broken.len()),
+                    );
+                }
+                ui.separator();
+
+                egui::ScrollArea::both().max_height(400.).show(ui, |ui| {
+                    egui::Grid::new("broken_references")
+                        .striped(true)
+                        .show(ui, |grid| {
+                            for (path, key, status) in broken.iter() {
+                                path_label(grid, path, &mut self.display_variant);
+                                binary_label(grid, key, &mut self.display_variant);
+                                grid.label(RichText::new(status.to_string()).color(Color32::RED));
+                                if grid.small_button("Inspect").clicked() {
+                                    tree.reveal_subtree(path);
+                                    tree.select_node(path.clone(), key.clone());
+                                }
+                                grid.end_row();
+                            }
+                        });
+                });
+            });
+        self.open = open;
+    }
+}
@@ -0,0 +1,349 @@
+//! Color palettes of the canvas with user overrides for element colors
+
+use std::collections::BTreeMap;
+
+use eframe::{
+    egui::{self, Visuals},
+    epaint::Color32,
+};
+use serde::{Deserialize, Serialize};
+use strum::{AsRefStr, EnumIter, IntoEnumIterator};
+
+use crate::model::Element;
+
+/// Storage key of the persisted theme
+pub(crate) const THEME_STORAGE_KEY: &str = "theme";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, EnumIter, AsRefStr)]
+pub(crate) enum Palette {
+    #[default]
+    Dark,
+    Light,
+    /// Okabe-Ito colors on black, distinguishable with color vision
+    /// deficiencies
+    #[strum(serialize = "High contrast")]
+    HighContrast,
+}
+
+/// Colors of canvas items
+struct Colors {
+    item: Color32,
+    sum_item: Color32,
+    reference: Color32,
+    subtree: Color32,
+    subtree_placeholder: Color32,
+    sumtree: Color32,
+    node_fill: Color32,
+    query_result_fill: Color32,
+    node_edge: Color32,
+    subtree_edge: Color32,
+    reference_edge: Color32,
+    hovered_edge: Color32,
+    incoming_edge: Color32,
+    minimap_subtree: Color32,
+    minimap_viewport: Color32,
+    /// Secondary text like hashes and paths
+    muted_text: Color32,
+    ok: Color32,
+    warning: Color32,
+    error: Color32,
+    /// Border of collapsed subtrees
+    frame_stroke: Color32,
+    /// Nodes known only by their hashes
+    ghost: Color32,
+}
+
+impl Palette {
+    fn colors(&self) -> Colors {
+        match self {
+            Palette::Dark => Colors {
+                item: Color32::WHITE,
+                sum_item: Color32::DARK_GREEN,
+                reference: Color32::LIGHT_BLUE,
+                subtree: Color32::GOLD,
+                subtree_placeholder: Color32::RED,
+                sumtree: Color32::GREEN,
+                node_fill: Color32::BLACK,
+                query_result_fill: Color32::from_rgb(50, 20, 60),
+                node_edge: Color32::GRAY,
+                subtree_edge: Color32::GOLD,
+                reference_edge: Color32::LIGHT_BLUE,
+                hovered_edge: Color32::WHITE,
+                incoming_edge: Color32::from_rgb(255, 165, 0),
+                minimap_subtree: Color32::from_gray(70),
+                minimap_viewport: Color32::WHITE,
+                muted_text: Color32::GRAY,
+                ok: Color32::LIGHT_GREEN,
+                warning: Color32::from_rgb(255, 165, 0),
+                error: Color32::RED,
+                frame_stroke: Color32::GRAY,
+                ghost: Color32::DARK_GRAY,
+            },
+            Palette::Light => Colors {
+                item: Color32::from_gray(30),
+                sum_item: Color32::DARK_GREEN,
+                reference: Color32::from_rgb(0, 90, 200),
+                subtree: Color32::from_rgb(170, 120, 0),
+                subtree_placeholder: Color32::from_rgb(200, 0, 0),
+                sumtree: Color32::from_rgb(0, 140, 0),
+                node_fill: Color32::WHITE,
+                query_result_fill: Color32::from_rgb(235, 215, 245),
+                node_edge: Color32::GRAY,
+                subtree_edge: Color32::from_rgb(170, 120, 0),
+                reference_edge: Color32::from_rgb(0, 90, 200),
+                hovered_edge: Color32::BLACK,
+                incoming_edge: Color32::from_rgb(230, 120, 0),
+                minimap_subtree: Color32::from_gray(190),
+                minimap_viewport: Color32::BLACK,
+                muted_text: Color32::from_gray(100),
+                ok: Color32::from_rgb(0, 130, 0),
+                warning: Color32::from_rgb(190, 100, 0),
+                error: Color32::from_rgb(200, 0, 0),
+                frame_stroke: Color32::from_gray(150),
+                ghost: Color32::from_gray(160),
+            },
+            Palette::HighContrast => Colors {
+                item: Color32::WHITE,
+                sum_item: Color32::from_rgb(0, 158, 115),
+                reference: Color32::from_rgb(86, 180, 233),
+                subtree: Color32::from_rgb(230, 159, 0),
+                subtree_placeholder: Color32::from_rgb(213, 94, 0),
+                sumtree: Color32::from_rgb(240, 228, 66),
+                node_fill: Color32::BLACK,
+                query_result_fill: Color32::from_rgb(40, 40, 90),
+                node_edge: Color32::from_gray(200),
+                subtree_edge: Color32::from_rgb(230, 159, 0),
+                reference_edge: Color32::from_rgb(86, 180, 233),
+                hovered_edge: Color32::WHITE,
+                incoming_edge: Color32::from_rgb(204, 121, 167),
+                minimap_subtree: Color32::from_gray(110),
+                minimap_viewport: Color32::WHITE,
+                muted_text: Color32::from_gray(200),
+                ok: Color32::from_rgb(0, 158, 115),
+                warning: Color32::from_rgb(240, 228, 66),
+                error: Color32::from_rgb(213, 94, 0),
+                frame_stroke: Color32::WHITE,
+                ghost: Color32::from_gray(140),
+            },
+        }
+    }
+}
+
+/// Palette picked by user with element colors overrides, persisted between
+/// sessions.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Theme {
+    pub(crate) palette: Palette,
+    /// RGBA colors by element names
+    element_overrides: BTreeMap<String, [u8; 4]>,
+}
+
+impl Theme {
+    pub(crate) fn from_json(json: &str) -> Option<Self> {
+        serde_json::from_str(json).ok()
+    }
+
+    pub(crate) fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("theme is serializable")
+    }
+
+    pub(crate) fn visuals(&self) -> Visuals {
+        match self.palette {
+            Palette::Dark => Visuals::dark(),
+            Palette::Light => Visuals::light(),
+            Palette::HighContrast => {
+                let mut visuals = Visuals::dark();
+                visuals.override_text_color = Some(Color32::WHITE);
+                visuals.panel_fill = Color32::BLACK;
+                visuals.window_fill = Color32::BLACK;
+                visuals.window_stroke.color = Color32::WHITE;
+                visuals
+            }
+        }
+    }
+
+    fn palette_element_color(&self, element: &Element) -> Color32 {
+        let colors = self.palette.colors();
+        match element {
+            Element::Item { .. } => colors.item,
+            Element::SumItem { .. } => colors.sum_item,
+            Element::Reference { .. } => colors.reference,
+            Element::Subtree { .. } => colors.subtree,
+            Element::SubtreePlaceholder => colors.subtree_placeholder,
+            Element::Sumtree { .. } => colors.sumtree,
+        }
+    }
+
+    pub(crate) fn element_color(&self, element: &Element) -> Color32 {
+        self.element_overrides
+            .get(element.as_ref())
+            .map(|[r, g, b, a]| Color32::from_rgba_unmultiplied(*r, *g, *b, *a))
+            .unwrap_or_else(|| self.palette_element_color(element))
+    }
+
+    pub(crate) fn is_overridden(&self, element: &Element) -> bool {
+        self.element_overrides.contains_key(element.as_ref())
+    }
+
+    pub(crate) fn set_element_color(&mut self, element: &Element, color: Color32) {
+        self.element_overrides
+            .insert(element.as_ref().to_owned(), color.to_srgba_unmultiplied());
+    }
+
+    pub(crate) fn reset_element_color(&mut self, element: &Element) {
+        self.element_overrides.remove(element.as_ref());
+    }
+
+    pub(crate) fn node_fill(&self) -> Color32 {
+        self.palette.colors().node_fill
+    }
+
+    pub(crate) fn query_result_fill(&self) -> Color32 {
+        self.palette.colors().query_result_fill
+    }
+
+    pub(crate) fn node_edge(&self) -> Color32 {
+        self.palette.colors().node_edge
+    }
+
+    pub(crate) fn subtree_edge(&self) -> Color32 {
+        self.palette.colors().subtree_edge
+    }
+
+    pub(crate) fn reference_edge(&self) -> Color32 {
+        self.palette.colors().reference_edge
+    }
+
+    pub(crate) fn hovered_edge(&self) -> Color32 {
+        self.palette.colors().hovered_edge
+    }
+
+    pub(crate) fn incoming_edge(&self) -> Color32 {
+        self.palette.colors().incoming_edge
+    }
+
+    pub(crate) fn minimap_subtree(&self) -> Color32 {
+        self.palette.colors().minimap_subtree
+    }
+
+    pub(crate) fn minimap_viewport(&self) -> Color32 {
+        self.palette.colors().minimap_viewport
+    }
+
+    pub(crate) fn muted_text(&self) -> Color32 {
+        self.palette.colors().muted_text
+    }
+
+    pub(crate) fn ok(&self) -> Color32 {
+        self.palette.colors().ok
+    }
+
+    pub(crate) fn warning(&self) -> Color32 {
+        self.palette.colors().warning
+    }
+
+    pub(crate) fn error(&self) -> Color32 {
+        self.palette.colors().error
+    }
+
+    pub(crate) fn frame_stroke(&self) -> Color32 {
+        self.palette.colors().frame_stroke
+    }
+
+    pub(crate) fn ghost(&self) -> Color32 {
+        self.palette.colors().ghost
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct ThemeWindow {
+    open: bool,
+}
+
+impl ThemeWindow {
+    pub(crate) fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+
+    pub(crate) fn show(&mut self, ctx: &egui::Context, theme: &mut Theme) {
+        egui::Window::new("Theme")
+            .open(&mut self.open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                egui::ComboBox::from_label("Palette")
+                    .selected_text(theme.palette.as_ref())
+                    .show_ui(ui, |menu| {
+                        Palette::iter().for_each(|palette| {
+                            menu.selectable_value(&mut theme.palette, palette, palette.as_ref());
+                        });
+                    });
+                ui.separator();
+
+                egui::Grid::new("element_colors").show(ui, |grid| {
+                    Element::iter().for_each(|element| {
+                        grid.label(element.as_ref());
+                        let mut color = theme.element_color(&element);
+                        if grid.color_edit_button_srgba(&mut color).changed() {
+                            theme.set_element_color(&element, color);
+                        }
+                        if grid
+                            .add_enabled(theme.is_overridden(&element), egui::Button::new("Reset"))
+                            .clicked()
+                        {
+                            theme.reset_element_color(&element);
+                        }
+                        grid.end_row();
+                    });
+                });
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overrides_survive_persistence() {
+        let mut theme = Theme {
+            palette: Palette::Light,
+            ..Default::default()
+        };
+        let item = Element::Item { value: vec![1] };
+        theme.set_element_color(&item, Color32::from_rgb(1, 2, 3));
+
+        let restored = Theme::from_json(&theme.to_json()).unwrap();
+        assert_eq!(restored, theme);
+        assert_eq!(restored.element_color(&item), Color32::from_rgb(1, 2, 3));
+
+        theme.reset_element_color(&item);
+        assert_eq!(theme.element_color(&item), Palette::Light.colors().item);
+    }
+
+    #[test]
+    fn canvas_colors_stand_out_from_node_fill() {
+        let lightness = |color: Color32| {
+            let [r, g, b, _] = color.to_array();
+            (r as i32 + g as i32 + b as i32) / 3
+        };
+        Palette::iter().for_each(|palette| {
+            let colors = palette.colors();
+            let fill = lightness(colors.node_fill);
+            [
+                colors.muted_text,
+                colors.ok,
+                colors.warning,
+                colors.error,
+                colors.frame_stroke,
+                colors.ghost,
+            ]
+            .into_iter()
+            .for_each(|color| {
+                assert!(
+                    (lightness(color) - fill).abs() >= 50,
+                    "{color:?} on {palette:?}"
+                )
+            });
+        });
+    }
+}
@@ -1,35 +1,228 @@
 //! Tree structure UI module
 
-use std::borrow::Borrow;
+use std::{borrow::Borrow, collections::BTreeMap};
 
 use eframe::{
     egui::{self, Id},
     emath::TSTransform,
-    epaint::{Color32, Pos2, Rect, Stroke},
+    epaint::{Pos2, Rect, Stroke, Vec2},
 };
+use strum::IntoEnumIterator;
 use tokio::sync::mpsc::Sender;
 
 use super::{
-    common::{binary_label_colored, path_label},
-    node::{draw_element, draw_node, element_to_color},
+    camera::CameraFocus,
+    common::{
+        binary_label_colored, bytes_as_hex, bytes_from_display_variant, display_variant_combo,
+        path_label_colored,
+    },
+    contracts::decoded_key_label,
+    download::save_file,
+    legend::ElementTypesFilter,
+    listing_filter::draw_listing_filter,
+    node::{draw_element, draw_node, hash_mismatch_label},
+    reference_edges::{
+        draw_incoming_edges, draw_reference_edges, ReferenceEdge, ReferenceEdgesMode,
+    },
+    theme::Theme,
 };
 use crate::{
     fetch::Message,
     model::{
-        alignment::{COLLAPSED_SUBTREE_WIDTH, NODE_HEIGHT},
-        Element, Key, KeySlice, NodeCtx, Path, SubtreeCtx, Tree,
+        alignment::{COLLAPSED_SUBTREE_WIDTH, NODE_HEIGHT, NODE_WIDTH},
+        contract::Contracts,
+        export::DataFormat,
+        paging::{PageCursor, DEFAULT_PAGE_SIZE},
+        Element, Key, NodeCtx, Path, SubtreeCtx, Tree,
     },
 };
 
-const KV_PER_PAGE: usize = 10;
+/// Extra space around the screen where nodes are drawn anyway
+const CULLING_MARGIN: f32 = 200.;
+
+/// Canvas drawing settings toggled by user
+#[derive(Debug)]
+pub(crate) struct TreeViewOptions {
+    /// Show AVL balance factors of nodes in expanded subtrees
+    pub(crate) avl_overlay: bool,
+    pub(crate) reference_edges: ReferenceEdgesMode,
+    /// Show an overview of the canvas in the corner
+    pub(crate) minimap: bool,
+    /// Number of keys listed in collapsed subtrees
+    pub(crate) page_size: usize,
+    pub(crate) theme: Theme,
+    /// Element types hidden or highlighted from the legend
+    pub(crate) element_types: ElementTypesFilter,
+}
+
+impl Default for TreeViewOptions {
+    fn default() -> Self {
+        TreeViewOptions {
+            avl_overlay: false,
+            reference_edges: Default::default(),
+            minimap: false,
+            page_size: DEFAULT_PAGE_SIZE,
+            theme: Default::default(),
+            element_types: Default::default(),
+        }
+    }
+}
+
+/// Draw a balance factor next to the node and outline the node if it
+/// violates the AVL invariant.
+fn draw_balance_overlay(ui: &mut egui::Ui, theme: &Theme, node_ctx: NodeCtx) {
+    let rect = ui.min_rect();
+    let painter = ui.painter();
+    let (text, color) = match node_ctx.subtree().node_heights(node_ctx.key()) {
+        Some(heights) => {
+            let text = format!(
+                "⚖ {:+} ({}|{})",
+                heights.balance_factor(),
+                heights.left,
+                heights.right
+            );
+            if heights.is_balanced() {
+                (text, theme.muted_text())
+            } else {
+                painter.rect_stroke(
+                    rect.expand(4.0),
+                    egui::Rounding::same(6.0),
+                    Stroke {
+                        width: 2.0,
+                        color: theme.error(),
+                    },
+                );
+                (text, theme.error())
+            }
+        }
+        None => ("⚖ ?".to_owned(), theme.ghost()),
+    };
+    painter.text(
+        rect.right_top() + egui::vec2(6.0, 0.0),
+        egui::Align2::LEFT_TOP,
+        text,
+        egui::FontId::monospace(12.0),
+        color,
+    );
+}
+
+/// Approximate rect of a node placed at its top center
+fn node_rect(coords: Pos2) -> Rect {
+    Rect::from_min_size(
+        coords - Vec2::new(NODE_WIDTH / 2., 0.),
+        Vec2::new(NODE_WIDTH, NODE_HEIGHT),
+    )
+}
+
+/// Page through subtree keys fetched from GroveDB in key order, shows
+/// whether all keys of the subtree are listed.
+fn draw_keys_pagination(
+    ui: &mut egui::Ui,
+    subtree_ctx: SubtreeCtx,
+    sender: &Sender<Message>,
+    theme: &Theme,
+    page_size: usize,
+) {
+    let subtree = subtree_ctx.subtree();
+    let page = subtree.keys_page();
+    let fetch_page = |cursor| {
+        // TODO error handling
+        let _ = sender.blocking_send(Message::FetchKeysPage {
+            path: subtree_ctx.path().clone(),
+            cursor,
+            page_size,
+        });
+    };
+    let (first_key, last_key) = match page {
+        Some(page) => (page.keys.first().cloned(), page.keys.last().cloned()),
+        None => (
+            subtree.nodes.keys().next().cloned(),
+            subtree.nodes.keys().take(page_size).last().cloned(),
+        ),
+    };
+
+    ui.horizontal(|pagination| {
+        if pagination.button("⏮").on_hover_text("First page").clicked() {
+            fetch_page(PageCursor::First);
+        }
+        if let Some(key) = first_key.filter(|_| page.is_some_and(|page| page.has_prev)) {
+            if pagination.button("⬅").clicked() {
+                fetch_page(PageCursor::Before(key));
+            }
+        } else {
+            pagination.add_enabled(false, egui::Button::new("⬅"));
+        }
+        if let Some(key) = last_key.filter(|_| page.is_none_or(|page| page.has_next)) {
+            if pagination.button("➡").clicked() {
+                fetch_page(PageCursor::After(key));
+            }
+        } else {
+            pagination.add_enabled(false, egui::Button::new("➡"));
+        }
+
+        match page {
+            Some(page) if page.is_complete() => {
+                pagination
+                    .label(egui::RichText::new("Complete").color(theme.ok()))
+                    .on_hover_text("All keys of the subtree are listed");
+            }
+            Some(_) => {
+                pagination
+                    .label(egui::RichText::new("Partial").color(theme.warning()))
+                    .on_hover_text("There are more keys on other pages");
+            }
+            None => {
+                pagination
+                    .label(egui::RichText::new("Loaded keys only").color(theme.muted_text()))
+                    .on_hover_text(
+                        "Only already fetched keys are listed, use the pages to list keys from \
+                         GroveDB",
+                    );
+            }
+        }
+    });
+
+    ui.horizontal(|jump| {
+        let mut display_variant = subtree.jump_key_display_variant_mut();
+        display_variant_combo(
+            jump,
+            (subtree_ctx.egui_id(), "jump_key"),
+            &mut display_variant,
+        );
+        let display_variant = *display_variant;
+        let mut jump_key = subtree.jump_key_mut();
+        jump.add(
+            egui::TextEdit::singleline(&mut *jump_key)
+                .hint_text(format!("Jump to key ({})", display_variant.name()))
+                .desired_width(COLLAPSED_SUBTREE_WIDTH - 200.),
+        );
+        let key =
+            bytes_from_display_variant(&jump_key, display_variant).filter(|key| !key.is_empty());
+        if jump
+            .add_enabled(key.is_some(), egui::Button::new("Go"))
+            .clicked()
+        {
+            fetch_page(PageCursor::From(key.expect("checked above")));
+        }
+    });
+}
 
 pub(crate) struct TreeDrawer<'u, 't> {
     ui: &'u mut egui::Ui,
     transform: TSTransform,
     rect: Rect,
-    references: Vec<(Pos2, Path, Key)>,
+    references: Vec<ReferenceEdge>,
+    /// Node under the pointer to draw references pointing to it
+    hovered: Option<(Path, Key)>,
+    /// Edges to parents of nodes which areas were not built being off-screen
+    culled_edges: Vec<[Pos2; 2]>,
+    /// Part of the canvas user asked to move the view to
+    focus: Option<CameraFocus>,
     tree: &'t Tree,
-    sender: &'t Sender<Message>,
+    /// `None` for read-only trees like proofs, nothing is fetched or unloaded
+    sender: Option<&'t Sender<Message>>,
+    contracts: &'t Contracts,
+    options: &'t TreeViewOptions,
 }
 
 impl<'u, 't> TreeDrawer<'u, 't> {
@@ -38,15 +231,22 @@ impl<'u, 't> TreeDrawer<'u, 't> {
         transform: TSTransform,
         rect: Rect,
         tree: &'t Tree,
-        sender: &'t Sender<Message>,
+        sender: Option<&'t Sender<Message>>,
+        contracts: &'t Contracts,
+        options: &'t TreeViewOptions,
     ) -> Self {
         Self {
             ui,
             transform,
             rect,
             references: vec![],
+            hovered: None,
+            culled_edges: vec![],
+            focus: None,
             tree,
             sender,
+            contracts,
+            options,
         }
     }
 
@@ -58,6 +258,7 @@ impl<'u, 't> TreeDrawer<'u, 't> {
     ) {
         let layer_response = egui::Area::new(Id::new(("area", node_ctx.egui_id())))
             .fixed_pos(coords)
+            .pivot(egui::Align2::CENTER_TOP)
             .order(egui::Order::Foreground)
             .show(self.ui.ctx(), |ui| {
                 ui.set_clip_rect(self.transform.inverse() * self.rect);
@@ -67,15 +268,32 @@ impl<'u, 't> TreeDrawer<'u, 't> {
                         [out_coords, node_ctx.node().ui_state.borrow().input_point],
                         Stroke {
                             width: 1.0,
-                            color: Color32::GRAY,
+                            color: self.options.theme.node_edge(),
                         },
                     );
                 }
 
-                draw_node(ui, self.sender, node_ctx);
+                ui.set_opacity(self.options.element_types.opacity(&node_ctx.node().element));
+                if let Some(focus) = draw_node(
+                    ui,
+                    self.sender,
+                    self.contracts,
+                    &self.options.theme,
+                    node_ctx,
+                ) {
+                    self.focus = Some(focus);
+                }
+
+                if self.options.avl_overlay {
+                    draw_balance_overlay(ui, &self.options.theme, node_ctx);
+                }
             })
             .response;
 
+        if layer_response.contains_pointer() {
+            self.hovered = Some((node_ctx.path().clone(), node_ctx.key().to_vec()));
+        }
+
         {
             let mut state = node_ctx.node().ui_state.borrow_mut();
             state.input_point = layer_response.rect.center_top();
@@ -88,90 +306,6 @@ impl<'u, 't> TreeDrawer<'u, 't> {
             .set_transform_layer(layer_response.layer_id, self.transform);
     }
 
-    fn draw_subtree_part<'a>(&mut self, mut coords: Pos2, node_ctx: NodeCtx<'a>) {
-        let subtree_ctx = node_ctx.subtree_ctx();
-        let mut current_level_nodes: Vec<Option<(Option<KeySlice>, KeySlice)>> = Vec::new();
-        let mut next_level_nodes: Vec<Option<(Option<KeySlice>, KeySlice)>> = Vec::new();
-        let mut level: u32 = 0;
-        let levels = node_ctx.subtree().levels();
-        let leafs = node_ctx.subtree().leafs();
-
-        current_level_nodes.push(Some((None, node_ctx.key())));
-
-        let max_width = node_ctx.subtree().width();
-        let x_base = coords.x - max_width / 2.;
-
-        let unit = max_width / leafs as f32;
-
-        while level <= levels {
-            coords.x = x_base;
-            if level < levels {
-                coords.x += 2u32.pow(levels - level - 1) as f32 * unit;
-            }
-
-            for item in current_level_nodes.drain(..) {
-                if let Some((parent_key, cur_node_ctx)) = item
-                    .map(|(p, k)| subtree_ctx.get_node(&k).map(|ctx| (p, ctx)))
-                    .flatten()
-                {
-                    let parent_out_coords = parent_key
-                        .map(|k| subtree_ctx.subtree().get_node_output(&k))
-                        .flatten();
-                    self.draw_node_area(parent_out_coords, coords, cur_node_ctx);
-
-                    let (node, _, key) = cur_node_ctx.split();
-
-                    if let Element::Reference { path, key } = &node.element {
-                        self.references.push((
-                            cur_node_ctx.node().ui_state.borrow().output_point,
-                            path.clone(),
-                            key.clone(),
-                        ));
-                    }
-
-                    next_level_nodes.push(
-                        cur_node_ctx
-                            .node()
-                            .ui_state
-                            .borrow()
-                            .show_left
-                            .then_some(
-                                node.left_child
-                                    .as_deref()
-                                    .map(|child_key| (Some(key), child_key)),
-                            )
-                            .flatten(),
-                    );
-
-                    next_level_nodes.push(
-                        cur_node_ctx
-                            .node()
-                            .ui_state
-                            .borrow()
-                            .show_right
-                            .then_some(
-                                node.right_child
-                                    .as_deref()
-                                    .map(|child_key| (Some(key), child_key)),
-                            )
-                            .flatten(),
-                    );
-                } else {
-                    next_level_nodes.push(None);
-                    next_level_nodes.push(None);
-                }
-                coords.x += 2u32.pow(levels - level) as f32 * unit;
-            }
-            if next_level_nodes.iter().all(Option::is_none) {
-                break;
-            }
-
-            coords.y += NODE_HEIGHT;
-            std::mem::swap(&mut current_level_nodes, &mut next_level_nodes);
-            level += 1;
-        }
-    }
-
     fn draw_subtree(&mut self, coords: Pos2, subtree_ctx: SubtreeCtx) {
         if subtree_ctx.subtree().is_expanded() {
             self.draw_subtree_expanded(coords, subtree_ctx);
@@ -184,18 +318,18 @@ impl<'u, 't> TreeDrawer<'u, 't> {
         let subtree = subtree_ctx.subtree();
         let layer_response = egui::Area::new(subtree_ctx.egui_id())
             .fixed_pos(coords)
+            .pivot(egui::Align2::CENTER_TOP)
             .order(egui::Order::Foreground)
             .show(self.ui.ctx(), |ui| {
                 ui.set_clip_rect(self.transform.inverse() * self.rect);
 
-                let mut stroke = Stroke::default();
-                stroke.width = 1.0;
+                let stroke = Stroke::new(1.0, self.options.theme.frame_stroke());
 
                 egui::Frame::default()
                     .rounding(egui::Rounding::same(4.0))
                     .inner_margin(egui::Margin::same(8.0))
                     .stroke(stroke)
-                    .fill(Color32::BLACK)
+                    .fill(self.options.theme.node_fill())
                     .show(ui, |ui| {
                         ui.style_mut().wrap = Some(false);
                         ui.collapsing("🖧", |menu| {
@@ -207,32 +341,83 @@ impl<'u, 't> TreeDrawer<'u, 't> {
                                 subtree_ctx.set_children_invisible();
                             }
 
-                            if menu.button("Fetch all").clicked() {
+                            if let Some(sender) = self.sender {
+                                if menu.button("Fetch all").clicked() {
+                                    if let Some(key) = &subtree.root_node {
+                                        // TODO error handling
+                                        let _ = sender.blocking_send(Message::FetchBranch {
+                                            path: subtree_ctx.path().clone(),
+                                            key: key.clone(),
+                                        });
+                                    }
+                                }
+
                                 if let Some(key) = &subtree.root_node {
-                                    // TODO error handling
-                                    let _ = self.sender.blocking_send(Message::FetchBranch {
-                                        path: subtree_ctx.path().clone(),
-                                        key: key.clone(),
-                                    });
+                                    if menu.button("Fetch root").clicked() {
+                                        // TODO error handling
+                                        let _ = sender.blocking_send(Message::FetchNode {
+                                            path: subtree_ctx.path().clone(),
+                                            key: key.clone(),
+                                        });
+                                    }
                                 }
                             }
 
-                            if let Some(key) = &subtree.root_node {
-                                if menu.button("Fetch root").clicked() {
+                            if menu.button("Zoom to subtree").clicked() {
+                                self.focus = Some(CameraFocus::Fit(subtree.bounding_rect()));
+                            }
+
+                            menu.horizontal(|line| {
+                                line.label("Export");
+                                for format in DataFormat::iter() {
+                                    if line.button(format.as_ref()).clicked() {
+                                        let descendants = *subtree.export_descendants_mut();
+                                        let data = format.write(
+                                            &self
+                                                .tree
+                                                .exported_nodes(subtree_ctx.path(), descendants),
+                                        );
+                                        let name = subtree_ctx
+                                            .path()
+                                            .last()
+                                            .map(|segment| bytes_as_hex(segment))
+                                            .unwrap_or_else(|| "root".to_owned());
+                                        *subtree.export_status_mut() = Some(
+                                            save_file(
+                                                &format!(
+                                                    "grovedbg-data-{name}.{}",
+                                                    format.extension()
+                                                ),
+                                                format.mime(),
+                                                data.as_bytes(),
+                                            )
+                                            .map_err(|error| error.to_string()),
+                                        );
+                                    }
+                                }
+                            });
+                            menu.checkbox(
+                                &mut subtree.export_descendants_mut(),
+                                "With descendant subtrees",
+                            );
+                            match &*subtree.export_status_mut() {
+                                Some(Ok(message)) => {
+                                    menu.colored_label(self.options.theme.ok(), message);
+                                }
+                                Some(Err(error)) => {
+                                    menu.colored_label(self.options.theme.error(), error);
+                                }
+                                None => {}
+                            }
+
+                            if let Some(sender) = self.sender {
+                                if menu.button("Unload").clicked() {
                                     // TODO error handling
-                                    let _ = self.sender.blocking_send(Message::FetchNode {
+                                    let _ = sender.blocking_send(Message::UnloadSubtree {
                                         path: subtree_ctx.path().clone(),
-                                        key: key.clone(),
                                     });
                                 }
                             }
-
-                            if menu.button("Unload").clicked() {
-                                // TODO error handling
-                                let _ = self.sender.blocking_send(Message::UnloadSubtree {
-                                    path: subtree_ctx.path().clone(),
-                                });
-                            }
                         });
 
                         ui.allocate_ui(
@@ -243,12 +428,40 @@ impl<'u, 't> TreeDrawer<'u, 't> {
                             |ui| ui.separator(),
                         );
 
-                        path_label(
+                        path_label_colored(
                             ui,
                             subtree_ctx.path(),
                             &mut subtree.path_display_variant_mut(),
+                            self.options.theme.muted_text(),
                         );
 
+                        let broken_references =
+                            self.tree.broken_references_count(subtree_ctx.path());
+                        if broken_references > 0 {
+                            ui.label(
+                                egui::RichText::new(format!(" ⚠ {broken_references} "))
+                                    .color(self.options.theme.node_fill())
+                                    .background_color(self.options.theme.error()),
+                            )
+                            .on_hover_text("Broken references in the subtree");
+                        }
+
+                        if let Some(documents_subtree) =
+                            self.contracts.documents_subtree(subtree_ctx.path())
+                        {
+                            ui.label(documents_subtree.label());
+                        }
+
+                        if let Some(root_hash) = subtree.root_hash() {
+                            ui.label(
+                                egui::RichText::new(format!(
+                                    "Root hash: {}",
+                                    bytes_as_hex(&root_hash)
+                                ))
+                                .color(self.options.theme.muted_text()),
+                            );
+                        }
+
                         ui.allocate_ui(
                             egui::Vec2 {
                                 x: COLLAPSED_SUBTREE_WIDTH - 50.,
@@ -257,28 +470,38 @@ impl<'u, 't> TreeDrawer<'u, 't> {
                             |ui| ui.separator(),
                         );
 
-                        for node_ctx in subtree_ctx
-                            .iter_nodes()
-                            .skip(subtree.page_idx() * KV_PER_PAGE)
-                            .take(KV_PER_PAGE)
-                        {
-                            if let Element::Reference {
-                                path: ref_path,
-                                key: ref_key,
-                            } = &node_ctx.node().element
-                            {
-                                if subtree_ctx.path() != ref_path {
-                                    self.references.push((
-                                        subtree.get_subtree_output_point(),
-                                        ref_path.clone(),
-                                        ref_key.clone(),
-                                    ));
-                                }
-                            }
-
-                            let color = element_to_color(&node_ctx.node().element);
+                        draw_listing_filter(
+                            ui,
+                            &self.options.theme,
+                            subtree_ctx,
+                            self.options.page_size,
+                        );
 
-                            ui.horizontal(|key_line| {
+                        let listed: Vec<_> = subtree_ctx
+                            .iter_listed_nodes(self.options.page_size)
+                            .filter(|node_ctx| {
+                                self.options
+                                    .element_types
+                                    .is_visible(&node_ctx.node().element)
+                            })
+                            .collect();
+                        // Rows anchors for references within the subtree
+                        let mut rows = BTreeMap::new();
+                        let mut inner_references = Vec::new();
+                        for node_ctx in listed.iter().copied() {
+                            ui.set_opacity(
+                                self.options.element_types.opacity(&node_ctx.node().element),
+                            );
+                            let color = self.options.theme.element_color(&node_ctx.node().element);
+
+                            let key_line_response = ui.horizontal(|key_line| {
+                                if let Some(hash) = &node_ctx.node().ghost {
+                                    key_line.label(
+                                        egui::RichText::new(format!("# {}", bytes_as_hex(hash)))
+                                            .color(self.options.theme.ghost()),
+                                    );
+                                    return;
+                                }
                                 if matches!(
                                     node_ctx.node().element,
                                     Element::Subtree { .. } | Element::Sumtree { .. }
@@ -292,13 +515,69 @@ impl<'u, 't> TreeDrawer<'u, 't> {
                                             .set_child_visibility(node_ctx.key(), visibility);
                                     }
                                 }
-                                binary_label_colored(
+                                if node_ctx.is_query_result() {
+                                    key_line
+                                        .label(egui::RichText::new("🔎").background_color(
+                                            self.options.theme.query_result_fill(),
+                                        ))
+                                        .on_hover_text("Path query result");
+                                }
+                                if binary_label_colored(
                                     key_line,
                                     node_ctx.key(),
                                     &mut node_ctx.node().ui_state.borrow_mut().key_display_variant,
                                     color,
-                                );
+                                )
+                                .clicked()
+                                {
+                                    node_ctx.select();
+                                }
+                                decoded_key_label(key_line, self.contracts, node_ctx);
+                                hash_mismatch_label(key_line, &self.options.theme, node_ctx);
+                                if let Element::Reference {
+                                    path: ref_path,
+                                    key: ref_key,
+                                } = &node_ctx.node().element
+                                {
+                                    if subtree_ctx.path() == ref_path
+                                        && !listed.iter().any(|listed| listed.key() == ref_key)
+                                    {
+                                        key_line
+                                            .label(
+                                                egui::RichText::new("↷")
+                                                    .color(self.options.theme.reference_edge()),
+                                            )
+                                            .on_hover_text(
+                                                "Target is in this subtree but not on this page",
+                                            );
+                                    }
+                                }
                             });
+                            let row_anchor = key_line_response.response.rect.right_center();
+                            rows.insert(node_ctx.key().to_vec(), row_anchor);
+                            if let Element::Reference {
+                                path: ref_path,
+                                key: ref_key,
+                            } = &node_ctx.node().element
+                            {
+                                let edge = ReferenceEdge {
+                                    from: row_anchor,
+                                    direction: Vec2::RIGHT,
+                                    source: (subtree_ctx.path().clone(), node_ctx.key().to_vec()),
+                                    target: (ref_path.clone(), ref_key.clone()),
+                                    to: None,
+                                };
+                                // Rows below aren't placed yet, the edge waits for them
+                                if subtree_ctx.path() == ref_path {
+                                    inner_references.push(edge);
+                                } else {
+                                    self.references.push(edge);
+                                }
+                            }
+                            if key_line_response.response.contains_pointer() {
+                                self.hovered =
+                                    Some((subtree_ctx.path().clone(), node_ctx.key().to_vec()));
+                            }
 
                             if matches!(
                                 node_ctx.node().element,
@@ -307,7 +586,7 @@ impl<'u, 't> TreeDrawer<'u, 't> {
                                     | Element::Sumtree { .. }
                                     | Element::Reference { .. }
                             ) {
-                                draw_element(ui, node_ctx);
+                                draw_element(ui, self.contracts, &self.options.theme, node_ctx);
                             }
 
                             ui.allocate_ui(
@@ -318,25 +597,22 @@ impl<'u, 't> TreeDrawer<'u, 't> {
                                 |ui| ui.separator(),
                             );
                         }
+                        ui.set_opacity(1.0);
+                        for mut edge in inner_references {
+                            if let Some(to) = rows.get(&edge.target.1) {
+                                edge.to = Some(*to);
+                                self.references.push(edge);
+                            }
+                        }
 
-                        if subtree.nodes.len() > KV_PER_PAGE {
-                            ui.horizontal(|pagination| {
-                                if pagination
-                                    .add_enabled(subtree.page_idx() > 0, egui::Button::new("⬅"))
-                                    .clicked()
-                                {
-                                    subtree.prev_page();
-                                }
-                                if pagination
-                                    .add_enabled(
-                                        (subtree.page_idx() + 1) * KV_PER_PAGE < subtree.n_nodes(),
-                                        egui::Button::new("➡"),
-                                    )
-                                    .clicked()
-                                {
-                                    subtree.next_page();
-                                }
-                            });
+                        if let Some(sender) = self.sender {
+                            draw_keys_pagination(
+                                ui,
+                                subtree_ctx,
+                                sender,
+                                &self.options.theme,
+                                self.options.page_size,
+                            );
                         }
                     });
             })
@@ -351,51 +627,126 @@ impl<'u, 't> TreeDrawer<'u, 't> {
     }
 
     fn draw_subtree_expanded(&mut self, coords: Pos2, subtree_ctx: SubtreeCtx) {
-        subtree_ctx.get_root().into_iter().for_each(|node_ctx| {
-            self.draw_subtree_part(coords, node_ctx);
-        });
+        let viewport = self.viewport();
+        for placement in subtree_ctx.subtree().expanded_layout() {
+            let Some(node_ctx) = subtree_ctx.get_node(&placement.key) else {
+                continue;
+            };
+            let parent_out_coords = placement
+                .parent
+                .and_then(|parent| subtree_ctx.subtree().get_node_output(&parent));
+            let node_coords = coords + placement.offset;
+            // Hidden nodes are left as junctions of the edges around them
+            let visible = self
+                .options
+                .element_types
+                .is_visible(&node_ctx.node().element);
+            if visible && viewport.intersects(node_rect(node_coords)) {
+                self.draw_node_area(parent_out_coords, node_coords, node_ctx);
+            } else {
+                self.skip_node_area(parent_out_coords, node_coords, node_ctx);
+            }
+            if !visible {
+                continue;
+            }
+
+            if let Element::Reference {
+                path: ref_path,
+                key: ref_key,
+            } = &node_ctx.node().element
+            {
+                self.references.push(ReferenceEdge {
+                    from: node_ctx.node().ui_state.borrow().output_point,
+                    direction: Vec2::DOWN,
+                    source: (subtree_ctx.path().clone(), placement.key.clone()),
+                    target: (ref_path.clone(), ref_key.clone()),
+                    to: None,
+                });
+            }
+        }
+    }
+
+    /// Canvas area visible on the screen in layers coordinates, slightly
+    /// enlarged since nodes sizes are known only approximately
+    fn viewport(&self) -> Rect {
+        (self.transform.inverse() * self.rect).expand(CULLING_MARGIN)
     }
 
-    pub(crate) fn draw_tree(mut self) {
-        self.tree.update_dimensions();
+    /// Update node's anchor points without building its area, edge to the
+    /// parent is kept if it crosses the viewport.
+    fn skip_node_area(&mut self, parent_coords: Option<Pos2>, coords: Pos2, node_ctx: NodeCtx) {
+        let mut state = node_ctx.node().ui_state.borrow_mut();
+        let height = state.output_point.y - state.input_point.y;
+        state.input_point = coords;
+        state.output_point = coords + Vec2::new(0., height);
+        if let Some(parent_coords) = parent_coords {
+            if self
+                .viewport()
+                .intersects(Rect::from_two_pos(parent_coords, coords))
+            {
+                self.culled_edges.push([parent_coords, coords]);
+            }
+        }
+    }
+
+    /// Keep anchor points and reference edges of a subtree which is out of the
+    /// viewport without drawing it.
+    fn skip_subtree(&mut self, coords: Pos2, subtree_ctx: SubtreeCtx) {
+        let subtree = subtree_ctx.subtree();
+        if subtree.is_expanded() {
+            self.draw_subtree_expanded(coords, subtree_ctx);
+            return;
+        }
+
+        let height = subtree.get_subtree_output_point().y
+            - subtree
+                .get_subtree_input_point()
+                .unwrap_or(subtree.get_subtree_output_point())
+                .y;
+        subtree.set_input_point(coords);
+        subtree.set_output_point(coords + Vec2::new(0., height));
+        for node_ctx in subtree_ctx
+            .iter_listed_nodes(self.options.page_size)
+            .filter(|node_ctx| {
+                self.options
+                    .element_types
+                    .is_visible(&node_ctx.node().element)
+            })
+        {
+            if let Element::Reference {
+                path: ref_path,
+                key: ref_key,
+            } = &node_ctx.node().element
+            {
+                if subtree_ctx.path() != ref_path {
+                    self.references.push(ReferenceEdge {
+                        from: subtree.get_subtree_output_point(),
+                        direction: Vec2::DOWN,
+                        source: (subtree_ctx.path().clone(), node_ctx.key().to_vec()),
+                        target: (ref_path.clone(), ref_key.clone()),
+                        to: None,
+                    });
+                }
+            }
+        }
+    }
 
-        let mut current_level = 0;
-        let mut current_height = 100.;
-        let mut current_parent = None;
-        let mut current_x_per_parent = 500.;
+    /// Draw visible subtrees and return the focus requested from node or
+    /// subtree menus if any.
+    pub(crate) fn draw_tree(mut self) -> Option<CameraFocus> {
+        self.tree.update_layout();
 
+        let viewport = self.viewport();
         for subtree_ctx in self
             .tree
             .iter_subtrees()
             .filter(|ctx| ctx.subtree().visible())
         {
-            let parent_path = if subtree_ctx.path().len() == 0 {
-                None
+            let position = subtree_ctx.subtree().position();
+            if viewport.intersects(subtree_ctx.subtree().bounding_rect()) {
+                self.draw_subtree(position, subtree_ctx);
             } else {
-                Some(&subtree_ctx.path()[0..subtree_ctx.path().len() - 1])
-            };
-            if current_parent != parent_path {
-                current_parent = parent_path;
-                if let Some(path) = current_parent {
-                    let path: Path = path.to_vec().into();
-                    let parent_subtree = self.tree.subtrees.get(&path).expect("parent must exist");
-                    current_x_per_parent = parent_subtree.get_subtree_input_point().unwrap().x
-                        - parent_subtree.width() / 2.0
-                        - COLLAPSED_SUBTREE_WIDTH / 2.0;
-                }
-            }
-            if subtree_ctx.path().len() > current_level {
-                current_height += self.tree.levels_dimentions.borrow()[current_level].1
-                    + self.tree.levels_dimentions.borrow()[current_level].0 * 0.05;
-                current_level += 1;
-            }
-
-            if subtree_ctx.path().len() > 0 {
-                current_x_per_parent += subtree_ctx.subtree().width() / 2.0;
-            }
-            self.draw_subtree(Pos2::new(current_x_per_parent, current_height), subtree_ctx);
-            if subtree_ctx.path().len() > 0 {
-                current_x_per_parent += subtree_ctx.subtree().width() / 2.0;
+                self.skip_subtree(position, subtree_ctx);
             }
 
             let root_in = subtree_ctx.subtree().get_subtree_input_point();
@@ -420,7 +771,7 @@ impl<'u, 't> TreeDrawer<'u, 't> {
                                 [out_point, in_point],
                                 Stroke {
                                     width: 1.0,
-                                    color: Color32::GOLD,
+                                    color: self.options.theme.subtree_edge(),
                                 },
                             );
                         })
@@ -436,30 +787,33 @@ impl<'u, 't> TreeDrawer<'u, 't> {
             .order(egui::Order::Background)
             .show(self.ui.ctx(), |ui| {
                 ui.set_clip_rect(self.transform.inverse() * self.rect);
-                let painter = ui.painter();
-
-                for (out_point, in_path, in_key) in self.references.into_iter() {
-                    let Some(in_point) = self
-                        .tree
-                        .subtrees
-                        .get(&in_path)
-                        .map(|subtree| subtree.get_node_input(&in_key))
-                        .flatten()
-                    else {
-                        continue;
-                    };
-                    painter.line_segment(
-                        [out_point, in_point],
+
+                for segment in self.culled_edges.iter() {
+                    ui.painter().line_segment(
+                        *segment,
                         Stroke {
                             width: 1.0,
-                            color: Color32::LIGHT_BLUE,
+                            color: self.options.theme.node_edge(),
                         },
                     );
                 }
+                draw_reference_edges(
+                    ui,
+                    self.transform,
+                    self.tree,
+                    &self.options.theme,
+                    self.references,
+                    self.options.reference_edges,
+                );
+                if let Some((path, key)) = &self.hovered {
+                    draw_incoming_edges(ui, self.tree, &self.options.theme, path, key);
+                }
             })
             .response;
         self.ui
             .ctx()
             .set_transform_layer(layer_response.layer_id, self.transform);
+
+        self.focus
     }
 }